@@ -0,0 +1,272 @@
+// The agent keeps the passphrase in memory behind a unix socket (like ssh-agent), so a single unlock
+// is enough for many rmt invocations. The passphrase never leaves the agent, rmt sends the salt of
+// the file it wants to encrypt or decrypt and the agent answers with the derived key.
+//
+// Protocol (one line per request and per answer):
+// PING              -> OK
+// DERIVE <hex salt> -> OK <hex key> | ERROR
+// STOP              -> OK (and the agent stops)
+
+pub const DEFAULT_AGENT_TIMEOUT: u64 = 900;
+
+#[cfg(unix)]
+mod unix {
+    use crate::data_manager;
+    use crate::encryption_manager::{self, EncryptionKey};
+    use crate::structure_manager::{get_agent_socket_path, get_path_in_trash};
+
+    use colored::Colorize;
+    use rusqlite::Connection;
+    use std::fs;
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    // Ask the passphrase, then run the agent in the background. The agent gives the key of the
+    // next encryptions too, so the passphrase is confirmed when no element is encrypted yet, and
+    // checked against an encrypted element otherwise.
+    pub fn start_agent(connection: &Connection, is_test: bool, timeout: u64) -> bool {
+        if is_agent_running(is_test) {
+            println!("The rmt agent is already running.");
+            return true;
+        }
+        let encrypted_item = data_manager::find_all_trash_items(connection, is_test)
+            .into_iter()
+            .find(|trash_item| trash_item.is_encrypted);
+        let encryption_key = match EncryptionKey::ask("Encryption key", encrypted_item.is_none()) {
+            Ok(encryption_key) => encryption_key,
+            _ => return false,
+        };
+        if let Some(trash_item) = encrypted_item {
+            let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+            if !encryption_manager::is_valid_key(&path_in_trash, &encryption_key) {
+                println!(
+                    "{}: this key doesn't decrypt the elements of the trash, the agent isn't started.",
+                    "Error".red().bold()
+                );
+                return false;
+            }
+        }
+        let encryption_key = match encryption_key {
+            EncryptionKey::Passphrase(passphrase) => passphrase,
+            EncryptionKey::Agent { .. } => return false,
+        };
+
+        let mut command = Command::new(std::env::current_exe().expect("Unable to find rmt"));
+        command
+            .arg("--agent-serve")
+            .arg("--agent-timeout")
+            .arg(timeout.to_string());
+        if is_test {
+            command.arg("--test");
+        }
+        // the agent has to outlive this process, so it is never waited
+        #[allow(clippy::zombie_processes)]
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Unable to start the rmt agent");
+        // the passphrase is given through stdin, so it never appears in the process list
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(format!("{}\n", encryption_key).as_bytes())
+            .expect("Unable to send the passphrase to the rmt agent");
+
+        // wait for the socket to be ready
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !is_agent_running(is_test) {
+            if Instant::now() > deadline {
+                println!("{}: the rmt agent didn't start.", "Error".red().bold());
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        println!(
+            "The rmt agent is running, it will forget the key in {} seconds.",
+            timeout.to_string().green().bold()
+        );
+        true
+    }
+
+    // Entry point of the background process started by start_agent
+    pub fn serve_agent_from_stdin(is_test: bool, timeout: u64) {
+        let mut passphrase = String::new();
+        io::stdin()
+            .read_line(&mut passphrase)
+            .expect("Unable to read the passphrase");
+        passphrase.pop();
+        serve_agent(is_test, &passphrase, timeout).expect("The rmt agent failed");
+    }
+
+    pub fn serve_agent(is_test: bool, passphrase: &str, timeout: u64) -> Result<(), io::Error> {
+        let socket_path = get_agent_socket_path(is_test);
+        // a socket left by an agent that didn't stop properly
+        if Path::new(&socket_path).exists() {
+            fs::remove_file(&socket_path)?;
+        }
+        let listener = UnixListener::bind(&socket_path)?;
+        fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+
+        let deadline = Instant::now() + Duration::from_secs(timeout);
+        while Instant::now() < deadline {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+                    if !handle_request(stream, passphrase) {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(50))
+                }
+                Err(_) => (),
+            }
+        }
+        fs::remove_file(&socket_path)
+    }
+
+    // Return false when the agent has to stop
+    fn handle_request(stream: UnixStream, passphrase: &str) -> bool {
+        let mut request = String::new();
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request).is_err() {
+            return true;
+        }
+        let mut arguments = request.split_whitespace();
+
+        let (answer, is_running) = match (arguments.next(), arguments.next()) {
+            (Some("PING"), _) => ("OK".to_string(), true),
+            (Some("STOP"), _) => ("OK".to_string(), false),
+            (Some("DERIVE"), Some(salt)) => match decode_hex(salt)
+                .and_then(|salt| encryption_manager::derive_key(passphrase, &salt).ok())
+            {
+                Some(key) => (format!("OK {}", encode_hex(&key)), true),
+                None => ("ERROR".to_string(), true),
+            },
+            _ => ("ERROR".to_string(), true),
+        };
+        let _ = (&stream).write_all(format!("{}\n", answer).as_bytes());
+        is_running
+    }
+
+    fn send_request(is_test: bool, request: &str) -> Result<String, io::Error> {
+        let stream = UnixStream::connect(get_agent_socket_path(is_test))?;
+        (&stream).write_all(format!("{}\n", request).as_bytes())?;
+        let mut answer = String::new();
+        BufReader::new(&stream).read_line(&mut answer)?;
+        Ok(answer.trim_end().to_string())
+    }
+
+    pub fn is_agent_running(is_test: bool) -> bool {
+        matches!(send_request(is_test, "PING").as_deref(), Ok("OK"))
+    }
+
+    pub fn stop_agent(is_test: bool) -> bool {
+        if send_request(is_test, "STOP").is_ok() {
+            println!("The rmt agent has been stopped.");
+        } else {
+            println!("The rmt agent is not running.");
+        }
+        true
+    }
+
+    pub fn derive_key(is_test: bool, salt: &[u8]) -> Result<Vec<u8>, io::Error> {
+        let answer = send_request(is_test, &format!("DERIVE {}", encode_hex(salt)))?;
+        answer
+            .strip_prefix("OK ")
+            .and_then(decode_hex)
+            .ok_or_else(|| io::Error::other("The rmt agent failed to derive the key"))
+    }
+
+    fn encode_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+        if !hex.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::structure_manager;
+
+        #[test]
+        fn test_hex() {
+            assert_eq!(encode_hex(&[0, 15, 255]), "000fff");
+            assert_eq!(decode_hex("000fff"), Some(vec![0, 15, 255]));
+            assert_eq!(decode_hex("0ff"), None);
+            assert_eq!(decode_hex("zz"), None);
+        }
+
+        #[test]
+        fn test_agent_derive_key() {
+            let is_test = true;
+            structure_manager::setup_structure(is_test);
+
+            let agent = std::thread::spawn(move || serve_agent(is_test, "rmt", 60));
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while !is_agent_running(is_test) && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+
+            let salt = [7u8; 32];
+            assert_eq!(
+                derive_key(is_test, &salt).unwrap(),
+                encryption_manager::derive_key("rmt", &salt).unwrap()
+            );
+
+            stop_agent(is_test);
+            agent.join().unwrap().unwrap();
+            assert!(!is_agent_running(is_test));
+            assert!(!Path::new(&get_agent_socket_path(is_test)).exists());
+
+            structure_manager::clear_structure(is_test);
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{derive_key, is_agent_running, serve_agent_from_stdin, start_agent, stop_agent};
+
+#[cfg(not(unix))]
+pub fn start_agent(_connection: &rusqlite::Connection, _is_test: bool, _timeout: u64) -> bool {
+    println!("The rmt agent is only available on unix systems.");
+    false
+}
+
+#[cfg(not(unix))]
+pub fn serve_agent_from_stdin(_is_test: bool, _timeout: u64) {}
+
+#[cfg(not(unix))]
+pub fn stop_agent(_is_test: bool) -> bool {
+    println!("The rmt agent is only available on unix systems.");
+    false
+}
+
+#[cfg(not(unix))]
+pub fn is_agent_running(_is_test: bool) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+pub fn derive_key(_is_test: bool, _salt: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    Err(std::io::Error::other(
+        "The rmt agent is only available on unix systems",
+    ))
+}
@@ -1,7 +1,15 @@
 use std::{fs, path::Path};
 
-use crate::{argument_errors::RmtArgumentErrors, structure_manager::relative_path_to_absolute};
+use crate::{
+    agent_manager,
+    argument_errors::RmtArgumentErrors,
+    display_manager::SizeUnits,
+    inspect_manager::ElementPath,
+    restore_manager::{ConflictPolicy, PathMap},
+    structure_manager::relative_path_to_absolute,
+};
 use clap::Parser;
+use regex::bytes::Regex;
 
 #[derive(Parser, Default, Debug)]
 #[clap(author = "Amine Zouitine", version, about)]
@@ -44,16 +52,185 @@ pub struct ArgumentsManager {
     #[arg(long = "ti")]
     pub is_trash_info: bool, // rmt trash_info or rmt ti
 
+    /// Display the sizes with SI units (kB, MB...) instead of binary units (KiB, MiB...)
+    #[arg(long = "si")]
+    pub is_si: bool,
+
+    /// Print the informations about the trash as JSON
+    #[arg(long = "json")]
+    pub is_json: bool,
+
     /// Flush all the elements present in the trash
     #[arg(long = "tf")]
     pub is_trash_flush: bool, // rmt trash_flush or rmt tf
 
+    /// Encrypt again the encrypted elements of the trash with a new key (all of them if no id is given)
+    #[arg(long = "rekey", num_args = 0.., value_name = "ID")]
+    pub rekey: Option<Vec<i32>>, // rmt --rekey or rmt --rekey 1 4
+
+    /// Check the content of the elements of the trash against their checksums (all of them if no id is given)
+    #[arg(long = "verify", num_args = 0.., value_name = "ID")]
+    pub verify: Option<Vec<i32>>, // rmt --verify or rmt --verify 1 4
+
+    /// Search the trash, the best matches first. The words are searched at the beginning of the words of the names and paths, and the query can contain conditions like path:~/work size>100M type:dir age<7d name:*.log
+    #[arg(long = "search", num_args = 1.., value_name = "QUERY")]
+    pub search: Option<Vec<String>>, // rmt --search report 2024
+
+    /// List the elements of the trash matching the query (all of them if no query is given), the newest first
+    #[arg(long = "list", num_args = 0.., value_name = "QUERY")]
+    pub list: Option<Vec<String>>, // rmt --list 'type:dir size>100M'
+
+    /// Remove for good the elements of the trash matching the query
+    #[arg(long = "purge", num_args = 1.., value_name = "QUERY")]
+    pub purge: Option<Vec<String>>, // rmt --purge 'age>30d'
+
+    /// Restore the elements of the trash matching the query
+    #[arg(long = "restore", num_args = 1.., value_name = "QUERY")]
+    pub restore: Option<Vec<String>>, // rmt --restore 'path:~/work name:*.rs'
+
+    /// What to do when an element is restored where another element already exists: keep both (the restored one is renamed "name (1)"), overwrite the existing one after moving it to the trash, merge the directories, skip the element or prompt for another directory
+    #[arg(long = "on-conflict", value_enum, value_name = "POLICY", default_value_t = ConflictPolicy::Prompt)]
+    pub on_conflict: ConflictPolicy, // rmt --restore 'path:~/work' --on-conflict rename
+
+    /// Recreate the original directory of a restored element when it doesn't exist anymore, with the permissions, owner and modification times its directories had
+    #[arg(long = "recreate-parents")]
+    pub is_recreating_parents: bool, // rmt --restore 'path:~/project' --recreate-parents
+
+    /// Restore (or extract) the elements in this directory instead of their original one, it is created if needed
+    #[arg(long = "to", value_name = "DIR")]
+    pub destination: Option<String>, // rmt --restore 'name:*.rs' --to ~/recovered
+
+    /// Restore the elements trashed from the OLD directory (or its subdirectories) in the NEW one instead, can be repeated
+    #[arg(long = "map", value_name = "OLD=NEW", value_parser = PathMap::parse, conflicts_with = "destination")]
+    pub path_maps: Vec<PathMap>, // rmt --restore 'path:/home/alice' --map /home/alice=/home/al
+
+    /// Restore some files of trashed directories, given by globs (* and ? stay in a directory, ** crosses them), or picked from a list when only the id is given
+    #[arg(long = "restore-files", num_args = 1.., value_name = "ID[/GLOB]", value_parser = ElementPath::parse)]
+    pub restore_files: Option<Vec<ElementPath>>, // rmt --restore-files '4/src/**/*.rs' 4/Cargo.toml
+
+    /// Remove the files restored by --restore-files from the trashed directory
+    #[arg(long = "prune", requires = "restore_files")]
+    pub is_pruning: bool, // rmt --restore-files 4/src --prune
+
+    /// Write a copy of elements of the trash in the --to directory (the current directory by default), they stay in the trash
+    #[arg(long = "extract", num_args = 1.., value_name = "ID")]
+    pub extract: Option<Vec<i32>>, // rmt --extract 4 --to /tmp
+
+    /// Write the content of a trashed file, or of a file inside a trashed directory, on the standard output
+    #[arg(long = "cat", value_name = "ID[/PATH]", value_parser = ElementPath::parse)]
+    pub cat: Option<ElementPath>, // rmt --cat 4/src/main.rs
+
+    /// List the entries of a trashed directory, or of a directory inside it, with their sizes
+    #[arg(long = "ls", value_name = "ID[/PATH]", value_parser = ElementPath::parse)]
+    pub ls: Option<ElementPath>, // rmt --ls 4/src
+
+    /// Compare a trashed element (or a path inside it) with a file or a directory on the disk, by default the one at its original path
+    #[arg(long = "diff", num_args = 1..=2, value_names = ["ID[/PATH]", "PATH"])]
+    pub diff: Option<Vec<String>>, // rmt --diff 4/src/main.rs ~/project/src/main.rs
+
+    /// Search the content of the trashed files (and of the files of the trashed directories) for a regular expression, "(?i)" at its beginning ignores the case
+    #[arg(long = "grep", value_name = "PATTERN", value_parser = Regex::new)]
+    pub grep: Option<Regex>, // rmt --grep 'fn \w+_manager'
+
+    /// Search the encrypted elements too, the encryption key is asked once (or given by the agent)
+    #[arg(long = "decrypt", requires = "grep")]
+    pub is_decrypting: bool, // rmt --grep password --decrypt
+
+    /// List the versions of a path, the elements trashed from it, numbered from the oldest one
+    #[arg(long = "versions", value_name = "PATH")]
+    pub versions: Option<String>, // rmt --versions ~/app/config.yml
+
+    /// Restore this version of the path given to --versions
+    #[arg(long = "restore-version", value_name = "N", requires = "versions")]
+    pub restore_version: Option<usize>, // rmt --versions ~/app/config.yml --restore-version 2
+
+    /// Show the elements taking the most space in the trash directory (10 if no number is given), and offer to purge some of them
+    #[arg(long = "top", num_args = 0..=1, default_missing_value = "10", value_name = "COUNT")]
+    pub top: Option<usize>, // rmt --top or rmt --top 20
+
+    /// Group the elements shown by --top by the directory they were trashed from
+    #[arg(long = "group")]
+    pub is_grouped: bool, // rmt --top --group
+
+    /// Pin elements of the trash, they are never purged to keep free space on the disk
+    #[arg(long = "pin", num_args = 1.., value_name = "ID")]
+    pub pin: Option<Vec<i32>>, // rmt --pin 1 4
+
+    /// Unpin elements of the trash
+    #[arg(long = "unpin", num_args = 1.., value_name = "ID")]
+    pub unpin: Option<Vec<i32>>, // rmt --unpin 1 4
+
+    /// Compress (and encrypt) the elements older than the cold_storage delay of the configuration
+    #[arg(long = "gc")]
+    pub is_gc: bool, // rmt --gc
+
+    /// Store the elements of the trash with the compression and encryption of the configuration (all of them if no id is given)
+    #[arg(long = "convert", num_args = 0.., value_name = "ID")]
+    pub convert: Option<Vec<i32>>, // rmt --convert or rmt --convert 1 4
+
+    /// Look for differences between the database and the trash directory, and offer to fix them
+    #[arg(long = "fsck")]
+    pub is_fsck: bool, // rmt --fsck
+
+    /// Start an agent keeping the encryption key in memory, so it is asked only once
+    #[arg(long = "agent")]
+    pub is_agent: bool, // rmt --agent
+
+    /// Stop the agent and forget the encryption key
+    #[arg(long = "agent-stop")]
+    pub is_agent_stop: bool, // rmt --agent-stop
+
+    /// Number of seconds before the agent forgets the encryption key
+    #[arg(long = "agent-timeout", value_name = "SECONDS", default_value_t = agent_manager::DEFAULT_AGENT_TIMEOUT)]
+    pub agent_timeout: u64,
+
+    // Run the agent itself, used by rmt --agent to start it in the background
+    #[arg(long = "agent-serve", hide = true)]
+    pub is_agent_serve: bool,
+
     /// Switch to test mode
     #[arg(long = "test")]
     pub is_test: bool, // rmt --test
 }
 
 impl ArgumentsManager {
+    // Commands working on the trash itself, they don't need any element to delete
+    pub fn has_trash_command(&self) -> bool {
+        self.is_trash_flush
+            || self.is_trash_display
+            || self.is_trash_info
+            || self.rekey.is_some()
+            || self.verify.is_some()
+            || self.search.is_some()
+            || self.list.is_some()
+            || self.purge.is_some()
+            || self.restore.is_some()
+            || self.restore_files.is_some()
+            || self.extract.is_some()
+            || self.cat.is_some()
+            || self.ls.is_some()
+            || self.diff.is_some()
+            || self.grep.is_some()
+            || self.versions.is_some()
+            || self.top.is_some()
+            || self.pin.is_some()
+            || self.unpin.is_some()
+            || self.is_gc
+            || self.convert.is_some()
+            || self.is_fsck
+            || self.is_agent
+            || self.is_agent_stop
+            || self.is_agent_serve
+    }
+
+    pub fn get_size_units(&self) -> SizeUnits {
+        if self.is_si {
+            SizeUnits::Si
+        } else {
+            SizeUnits::Iec
+        }
+    }
+
     pub fn filter_all_errors(&mut self) -> i32 {
         let mut exit_code = 0;
         let mut result: Vec<String> = Vec::new();
@@ -0,0 +1,277 @@
+use crate::encryption_manager::{DecryptReader, EncryptionKey};
+use crate::structure_manager::get_path_in_trash;
+use crate::{data_manager, trash_item::TrashItem};
+
+use colored::Colorize;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, copy, Cursor, Read, Seek};
+use std::path::{Path, MAIN_SEPARATOR};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+// Checksums of the content of an element.
+// For a directory, every file has its own checksum and the checksum of the directory is
+// computed from the list of (path, checksum), so a renamed file is also detected.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Checksums {
+    pub content_hash: String,
+    pub files: Vec<(String, String)>, // (path inside the directory, checksum), sorted by path
+}
+
+fn compute_reader_checksum<R: Read>(reader: &mut R) -> Result<String, io::Error> {
+    let mut hasher = Sha256::new();
+    copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn compute_directory_checksum(files: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    files.iter().for_each(|(inner_path, digest)| {
+        hasher.update(format!("{}  {}\n", digest, inner_path).as_bytes())
+    });
+    format!("{:x}", hasher.finalize())
+}
+
+fn to_checksums(mut files: Vec<(String, String)>) -> Checksums {
+    files.sort();
+    Checksums {
+        content_hash: compute_directory_checksum(&files),
+        files,
+    }
+}
+
+// Paths inside a directory always use '/', like inside a zip archive
+fn to_inner_path(relative_path: &Path) -> String {
+    relative_path.to_string_lossy().replace(MAIN_SEPARATOR, "/")
+}
+
+// Compute the checksums of a file or a directory present on the disk
+pub fn compute_element_checksums(element_path: &str) -> Result<Checksums, io::Error> {
+    let base_path = Path::new(element_path);
+    let file_type = base_path.metadata()?.file_type();
+    if !file_type.is_dir() && !file_type.is_file() {
+        // reading a FIFO would block
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file",
+        ));
+    }
+    if !file_type.is_dir() {
+        return Ok(Checksums {
+            content_hash: compute_reader_checksum(&mut File::open(base_path)?)?,
+            files: Vec::new(),
+        });
+    }
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(base_path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let inner_path = to_inner_path(entry.path().strip_prefix(base_path).unwrap());
+            let digest = compute_reader_checksum(&mut File::open(entry.path())?)?;
+            files.push((inner_path, digest));
+        }
+    }
+    Ok(to_checksums(files))
+}
+
+fn compute_archive_checksums<R: Read + Seek>(
+    reader: R,
+    is_folder: bool,
+) -> Result<Checksums, io::Error> {
+    let mut zip_arc = ZipArchive::new(reader)?;
+
+    let mut files = Vec::new();
+    for i in 0..zip_arc.len() {
+        let mut f = zip_arc.by_index(i)?;
+        if f.is_dir() {
+            continue;
+        }
+        let inner_path = f.name().replace('\\', "/");
+        files.push((inner_path, compute_reader_checksum(&mut f)?));
+    }
+
+    if is_folder {
+        Ok(to_checksums(files))
+    } else {
+        let (_, content_hash) = files
+            .pop()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Empty archive"))?;
+        Ok(Checksums {
+            content_hash,
+            files: Vec::new(),
+        })
+    }
+}
+
+// Compute the checksums of an element stored in the trash, decrypting and decompressing it on the fly
+pub fn compute_stored_checksums(
+    trash_item: &TrashItem,
+    is_test: bool,
+) -> Result<Checksums, io::Error> {
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+
+    if trash_item.is_compressed && trash_item.is_encrypted {
+        let mut decrypt_reader = DecryptReader::new(
+            File::open(&path_in_trash)?,
+            &EncryptionKey::get(is_test, false)?,
+        )?;
+        // a zip archive needs to be seekable
+        let mut archive = Vec::new();
+        decrypt_reader.read_to_end(&mut archive)?;
+        compute_archive_checksums(Cursor::new(archive), trash_item.is_folder)
+    } else if trash_item.is_compressed {
+        compute_archive_checksums(File::open(&path_in_trash)?, trash_item.is_folder)
+    } else if trash_item.is_encrypted {
+        let mut decrypt_reader = DecryptReader::new(
+            File::open(&path_in_trash)?,
+            &EncryptionKey::get(is_test, false)?,
+        )?;
+        Ok(Checksums {
+            content_hash: compute_reader_checksum(&mut decrypt_reader)?,
+            files: Vec::new(),
+        })
+    } else {
+        compute_element_checksums(&path_in_trash)
+    }
+}
+
+// Display the files of a directory that don't match the checksums computed when it was trashed
+fn display_directory_differences(expected_files: &[(String, String)], files: &[(String, String)]) {
+    expected_files.iter().for_each(|(inner_path, digest)| {
+        match files.iter().find(|(path, _)| path == inner_path) {
+            Some((_, current_digest)) if current_digest != digest => {
+                println!("    {} {}", "changed:".red().bold(), inner_path)
+            }
+            None => println!("    {} {}", "missing:".red().bold(), inner_path),
+            _ => (),
+        }
+    });
+    files
+        .iter()
+        .filter(|(inner_path, _)| !expected_files.iter().any(|(path, _)| path == inner_path))
+        .for_each(|(inner_path, _)| println!("    {} {}", "unexpected:".red().bold(), inner_path));
+}
+
+// Check the content of the selected elements (or all the elements) of the trash against the
+// checksums computed when they were trashed
+pub fn verify_elements(connection: &Connection, is_test: bool, trash_items_ids: &[i32]) -> bool {
+    let trash_items = data_manager::find_selected_trash_items(connection, is_test, trash_items_ids);
+
+    let mut is_success = true;
+    for trash_item in trash_items.iter() {
+        let content_hash = match &trash_item.content_hash {
+            Some(content_hash) => content_hash,
+            None => {
+                println!(
+                    "{} {} has no checksum (trashed by an older version of rmt).",
+                    "?".bold(),
+                    trash_item.name.bold()
+                );
+                continue;
+            }
+        };
+
+        match compute_stored_checksums(trash_item, is_test) {
+            Ok(checksums) if &checksums.content_hash == content_hash => {
+                println!("{} {}", "✔".green().bold(), trash_item.name.green().bold())
+            }
+            Ok(checksums) => {
+                is_success = false;
+                println!(
+                    "{} {} doesn't match its checksum.",
+                    "✘".red().bold(),
+                    trash_item.name.red().bold()
+                );
+                if trash_item.is_folder {
+                    let expected_files =
+                        data_manager::find_checksums(connection, &trash_item.hash, is_test)
+                            .unwrap_or_default();
+                    display_directory_differences(&expected_files, &checksums.files);
+                }
+            }
+            Err(e) => {
+                is_success = false;
+                println!(
+                    "{} {} can't be read ({}).",
+                    "✘".red().bold(),
+                    trash_item.name.red().bold(),
+                    e
+                );
+            }
+        }
+    }
+    is_success
+}
+
+// Check a restored element against the checksum computed when it was trashed
+pub fn verify_restored_element(trash_item: &TrashItem, restored_path: &str) {
+    let content_hash = match &trash_item.content_hash {
+        Some(content_hash) => content_hash,
+        None => return,
+    };
+
+    match compute_element_checksums(restored_path) {
+        Ok(checksums) if &checksums.content_hash == content_hash => (),
+        _ => println!(
+            "{}: the content of {} doesn't match the checksum computed when it was trashed.\r",
+            "Warning".red().bold(),
+            restored_path.red().bold()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager;
+    use std::fs;
+
+    #[test]
+    fn test_compute_element_checksums() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+
+        let directory_path = format!(
+            "{}{}checksum_directory",
+            structure_manager::get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        fs::create_dir_all(format!("{}{}src", directory_path, MAIN_SEPARATOR)).unwrap();
+        let file_path = format!("{}{}a.txt", directory_path, MAIN_SEPARATOR);
+        fs::write(&file_path, "rmt").unwrap();
+        fs::write(
+            format!(
+                "{}{}src{}b.rs",
+                directory_path, MAIN_SEPARATOR, MAIN_SEPARATOR
+            ),
+            "fn main() {}",
+        )
+        .unwrap();
+
+        let file_checksums = compute_element_checksums(&file_path).unwrap();
+        assert_eq!(file_checksums.content_hash, sha256::digest("rmt"));
+        assert!(file_checksums.files.is_empty());
+
+        let directory_checksums = compute_element_checksums(&directory_path).unwrap();
+        assert_eq!(
+            directory_checksums.files,
+            vec![
+                ("a.txt".to_string(), sha256::digest("rmt")),
+                ("src/b.rs".to_string(), sha256::digest("fn main() {}")),
+            ]
+        );
+
+        fs::write(&file_path, "rmt.rs").unwrap();
+        assert_ne!(
+            compute_element_checksums(&directory_path)
+                .unwrap()
+                .content_hash,
+            directory_checksums.content_hash
+        );
+
+        structure_manager::clear_structure(is_test);
+    }
+}
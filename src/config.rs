@@ -8,6 +8,30 @@ pub struct Config {
     pub encryption: bool,
     // Defines the set of element rules to be checked before adding an element to the trash or removing it
     pub trash: Option<Trash>,
+    // How the deletion dates are displayed: local, iso or relative
+    #[serde(default)]
+    pub date_format: DateFormat,
+    // Elements to compress (and encrypt) with rmt --gc once they are old enough
+    #[serde(default)]
+    pub cold_storage: Option<ColdStorage>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    #[default]
+    Local, // 2023-04-01 18:30:00 in the local timezone
+    Iso,      // 2023-04-01T16:30:00Z
+    Relative, // 3 hours ago
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColdStorage {
+    // Number of days after the deletion before an element is compressed
+    pub after_days: u32,
+    // Encrypt the elements too when they are compressed
+    #[serde(default)]
+    pub encryption: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -16,6 +40,12 @@ pub struct Trash {
     pub max_size: Option<u32>,
     // maximum number of elements allowed in the trash
     pub max_element: Option<u32>,
+    // Percentage of the filesystem of the trash to keep free, the oldest unpinned elements are
+    // purged when there is less free space
+    pub min_free_space: Option<u8>,
+    // Number of versions kept for each path, the oldest unpinned versions of a path are purged
+    // when it is trashed again
+    pub max_versions: Option<u32>,
 }
 
 impl Config {
@@ -25,6 +55,8 @@ impl Config {
             compression: false,
             encryption: false,
             trash: None,
+            date_format: DateFormat::default(),
+            cold_storage: None,
         }
     }
 }
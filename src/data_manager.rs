@@ -1,16 +1,17 @@
+use crate::query_manager::Query;
+use crate::restore_manager::DirectoryMetadata;
 use crate::trash_item::TrashItem;
 use crate::{database_errors::RmtDataBaseErrors, structure_manager};
 
 use field_count::FieldCount;
-use rusqlite::{params, types::FromSql, Connection, Row};
-use std::path::{Path, MAIN_SEPARATOR};
+use rusqlite::types::{FromSql, Value};
+use rusqlite::{params, params_from_iter, Connection, Row};
+use serde::Serialize;
 use std::process::exit;
 
+// The consistency between the database and the trash directory is checked by rmt --fsck
 pub fn setup_data_base(is_test: bool) -> Connection {
-    let connection = create_database(is_test);
-    verification_database_item_exist_in_trash_folder(&connection, is_test);
-
-    connection
+    create_database(is_test)
 }
 
 // Create the database and the table to save information about deleted elements. (if database doesn't already exist)
@@ -19,25 +20,8 @@ pub fn create_database(is_test: bool) -> Connection {
     let table_name = structure_manager::get_data_base_table_name(is_test);
 
     let cols = get_column_count(&connection, is_test);
-    if cols == 0 {
-        let stmt_result = connection.execute(
-            &format!(
-                "CREATE TABLE IF NOT EXISTS {} (
-                 id INTEGER PRIMARY KEY,
-                 name TEXT NOT NULL,
-                 hash NOT NULL UNIQUE,
-                 path TEXT NOT NULL,
-                 date TEXT NOT NULL,
-                 real_size INTEGER NOT NULL,
-                 compression_size INTEGER,
-                 is_folder INTEGER NOT NULL,
-                 is_encrypted INTEGER NOT NULL,
-                 is_compressed INTEGER NOT NULL
-                )",
-                table_name
-            ),
-            [],
-        );
+    let connection = if cols == 0 {
+        let stmt_result = connection.execute(&get_trash_table_schema(&table_name), []);
 
         match stmt_result {
             Ok(_) => connection,
@@ -47,22 +31,250 @@ pub fn create_database(is_test: bool) -> Connection {
             }
         }
     } else if cols != TrashItem::field_count() {
+        add_missing_columns(&connection, is_test);
+        connection
+    } else {
+        connection
+    };
+
+    migrate_date_column(&connection, is_test);
+
+    create_trash_table_indexes(&connection, is_test);
+    create_search_table(&connection, is_test);
+    create_checksum_table(&connection, is_test);
+    create_journal_table(&connection, is_test);
+    create_directory_table(&connection, is_test);
+    connection
+}
+
+// Indexes used to sort the pages of the trash display without reading the whole table, and to
+// find the versions of a path
+fn create_trash_table_indexes(connection: &Connection, is_test: bool) {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let stmt_result = connection.execute_batch(&format!(
+        "CREATE INDEX IF NOT EXISTS {0}_name ON {0} (name);
+        CREATE INDEX IF NOT EXISTS {0}_real_size ON {0} (real_size);
+        CREATE INDEX IF NOT EXISTS {0}_path_name ON {0} (path, name);",
+        table_name
+    ));
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DataBaseCreation);
+        exit(1);
+    }
+}
+
+fn get_trash_table_schema(table_name: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+         id INTEGER PRIMARY KEY,
+         name TEXT NOT NULL,
+         hash NOT NULL UNIQUE,
+         path TEXT NOT NULL,
+         date INTEGER NOT NULL,
+         real_size INTEGER NOT NULL,
+         compression_size INTEGER,
+         is_folder INTEGER NOT NULL,
+         is_encrypted INTEGER NOT NULL,
+         is_compressed INTEGER NOT NULL,
+         content_hash TEXT,
+         is_pinned INTEGER NOT NULL DEFAULT 0
+        )",
+        table_name
+    )
+}
+
+// The first versions of rmt saved the date as a local time text ("%Y-%m-%d %H:%M:%S"), it is now
+// a UTC timestamp. The type of a column can't be changed, so the table is copied into a new one.
+fn migrate_date_column(connection: &Connection, is_test: bool) {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut date_type = String::new();
+    let stmt_result = connection.pragma(None, "table_info", &table_name, |row| {
+        if row.get::<_, String>(1)? == "date" {
+            date_type = row.get(2)?;
+        }
+        Ok(())
+    });
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::CountAllColumns);
+        exit(1);
+    }
+    if date_type != "TEXT" {
+        return;
+    }
+
+    let migration_table_name = format!("{}_migration", table_name);
+    // the 'utc' modifier converts the local time to UTC, with the timezone rules of the date itself
+    let stmt_result = connection.execute_batch(&format!(
+        "BEGIN;
+        {2};
+        INSERT INTO {1} SELECT id, name, hash, path,
+         COALESCE(CAST(strftime('%s', date, 'utc') AS INTEGER), 0),
+         real_size, compression_size, is_folder, is_encrypted, is_compressed, content_hash,
+         is_pinned FROM {0};
+        DROP TABLE {0};
+        ALTER TABLE {1} RENAME TO {0};
+        COMMIT;",
+        table_name,
+        migration_table_name,
+        get_trash_table_schema(&migration_table_name)
+    ));
+    if stmt_result.is_err() {
+        let _ = connection.execute_batch("ROLLBACK");
+        println!("Failed to migrate the 'date' column of the existing trash database");
+        exit(1);
+    }
+}
+
+// Columns added after the first version of the table, with the definition used to add them to an existing database
+const ADDED_COLUMNS: [(&str, &str); 3] = [
+    ("is_compressed", "INTEGER NOT NULL DEFAULT 0"),
+    ("content_hash", "TEXT"),
+    ("is_pinned", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+fn add_missing_columns(connection: &Connection, is_test: bool) {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut column_names = Vec::<String>::new();
+    let stmt_result = connection.pragma(None, "table_info", &table_name, |row| {
+        column_names.push(row.get(1)?);
+        Ok(())
+    });
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::CountAllColumns);
+        exit(1);
+    }
+
+    for (column_name, column_definition) in ADDED_COLUMNS {
+        if column_names.iter().any(|name| name == column_name) {
+            continue;
+        }
         let stmt_result = connection.execute(
             &format!(
-                "ALTER TABLE {} ADD COLUMN is_compressed INTEGER NOT NULL DEFAULT 0",
-                table_name
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table_name, column_name, column_definition
             ),
             [],
         );
-        match stmt_result {
-            Ok(_) => connection,
-            Err(_) => {
-                println!("Failed to add 'is_compressed' column to the existing trash database");
-                exit(1);
-            }
+        if stmt_result.is_err() {
+            println!(
+                "Failed to add '{}' column to the existing trash database",
+                column_name
+            );
+            exit(1);
         }
-    } else {
-        connection
+    }
+}
+
+// Full-text index over the names and the paths of the elements. It doesn't store the text itself,
+// it reads it from the trash table, and triggers keep it in sync with every change of the trash table.
+fn create_search_table(connection: &Connection, is_test: bool) {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let search_table_name = structure_manager::get_search_table_name(is_test);
+
+    let is_existing_table = connection
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [&search_table_name],
+            |row| row.get::<_, usize>(0),
+        )
+        .map(|count| count > 0)
+        .unwrap_or(false);
+
+    let stmt_result = connection.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS {1} USING fts5(
+         name, path, content='{0}', content_rowid='id', prefix='2 3'
+        );
+        CREATE TRIGGER IF NOT EXISTS {1}_insert AFTER INSERT ON {0} BEGIN
+         INSERT INTO {1} (rowid, name, path) VALUES (new.id, new.name, new.path);
+        END;
+        CREATE TRIGGER IF NOT EXISTS {1}_delete AFTER DELETE ON {0} BEGIN
+         INSERT INTO {1} ({1}, rowid, name, path) VALUES ('delete', old.id, old.name, old.path);
+        END;
+        CREATE TRIGGER IF NOT EXISTS {1}_update AFTER UPDATE ON {0} BEGIN
+         INSERT INTO {1} ({1}, rowid, name, path) VALUES ('delete', old.id, old.name, old.path);
+         INSERT INTO {1} (rowid, name, path) VALUES (new.id, new.name, new.path);
+        END;",
+        table_name, search_table_name
+    ));
+    // the elements trashed before the creation of the index have to be indexed
+    let stmt_result = stmt_result.and_then(|_| {
+        if is_existing_table {
+            Ok(())
+        } else {
+            connection.execute_batch(&format!(
+                "INSERT INTO {0} ({0}) VALUES ('rebuild')",
+                search_table_name
+            ))
+        }
+    });
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DataBaseCreation);
+        exit(1);
+    }
+}
+
+// The checksum of every file inside a trashed directory
+fn create_checksum_table(connection: &Connection, is_test: bool) {
+    let checksum_table_name = structure_manager::get_checksum_table_name(is_test);
+    let stmt_result = connection.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {0} (
+         id INTEGER PRIMARY KEY,
+         item_hash TEXT NOT NULL,
+         inner_path TEXT NOT NULL,
+         digest TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS {0}_item_hash ON {0} (item_hash);",
+        checksum_table_name
+    ));
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DataBaseCreation);
+        exit(1);
+    }
+}
+
+// The operations in progress, see journal_manager
+fn create_journal_table(connection: &Connection, is_test: bool) {
+    let journal_table_name = structure_manager::get_journal_table_name(is_test);
+    let stmt_result = connection.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+             id INTEGER PRIMARY KEY,
+             entry TEXT NOT NULL
+            )",
+            journal_table_name
+        ),
+        [],
+    );
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DataBaseCreation);
+        exit(1);
+    }
+}
+
+// The metadata of the parent directories of the trashed elements, see restore_manager
+fn create_directory_table(connection: &Connection, is_test: bool) {
+    let directory_table_name = structure_manager::get_directory_table_name(is_test);
+    let stmt_result = connection.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+             path TEXT PRIMARY KEY,
+             mode INTEGER NOT NULL,
+             modified INTEGER NOT NULL,
+             uid INTEGER NOT NULL,
+             gid INTEGER NOT NULL
+            )",
+            directory_table_name
+        ),
+        [],
+    );
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DataBaseCreation);
+        exit(1);
     }
 }
 
@@ -82,24 +294,6 @@ fn get_column_count(connection: &Connection, is_test: bool) -> usize {
     }
 }
 
-// Check if every element from the database exist on the trash
-// It's prevent error if user delete (without using rmt --td or rm --tf) element directly on the trash folder
-pub fn verification_database_item_exist_in_trash_folder(connection: &Connection, is_test: bool) {
-    let trash_items = find_all_trash_items(connection, is_test);
-
-    trash_items.iter().for_each(|item| {
-        let path = format!(
-            "{}{}{}",
-            structure_manager::get_trash_directory_path(is_test),
-            MAIN_SEPARATOR,
-            item.hash
-        );
-        if !Path::new(&path).exists() {
-            delete_trash_item(connection, item.id, is_test);
-        }
-    })
-}
-
 fn get<T: FromSql>(row: &Row, index: usize) -> T {
     match row.get(index) {
         Ok(element) => element,
@@ -110,6 +304,23 @@ fn get<T: FromSql>(row: &Row, index: usize) -> T {
     }
 }
 
+fn row_to_trash_item(row: &Row) -> TrashItem {
+    TrashItem {
+        id: get(row, 0),
+        name: get(row, 1),
+        hash: get(row, 2),
+        path: get(row, 3),
+        date: get(row, 4),
+        real_size: get(row, 5),
+        compression_size: get(row, 6),
+        is_folder: get(row, 7),
+        is_encrypted: get(row, 8),
+        is_compressed: get(row, 9),
+        content_hash: get(row, 10),
+        is_pinned: get(row, 11),
+    }
+}
+
 // Find all elements on the table and convert them to TrashItems
 pub fn find_all_trash_items(connection: &Connection, is_test: bool) -> Vec<TrashItem> {
     let table_name = structure_manager::get_data_base_table_name(is_test);
@@ -124,20 +335,7 @@ pub fn find_all_trash_items(connection: &Connection, is_test: bool) -> Vec<Trash
 
     let mut trash_items = Vec::<TrashItem>::new();
 
-    let items = stmt.query_map((), |row| {
-        Ok(TrashItem {
-            id: get(row, 0),
-            name: get(row, 1),
-            hash: get(row, 2),
-            path: get(row, 3),
-            date: get(row, 4),
-            real_size: get(row, 5),
-            compression_size: get(row, 6),
-            is_folder: get(row, 7),
-            is_encrypted: get(row, 8),
-            is_compressed: get(row, 9),
-        })
-    });
+    let items = stmt.query_map((), |row| Ok(row_to_trash_item(row)));
 
     for item in items.unwrap() {
         trash_items.push(item.unwrap());
@@ -147,7 +345,170 @@ pub fn find_all_trash_items(connection: &Connection, is_test: bool) -> Vec<Trash
     trash_items.into_iter().rev().collect()
 }
 
-// Get a trash item by id, need to refactor because it's not the best way to do it
+// Order of the trash items in a page, the newest elements come first by default
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrashItemsOrder {
+    #[default]
+    Newest,
+    Oldest,
+    Name,
+    Size,
+    Relevance, // best matches of the search first, the newest elements when there is no search
+}
+
+impl TrashItemsOrder {
+    // The id grows with the adding date, so sorting by id is the same as sorting by date
+    fn to_sql(self, search_table_name: Option<&str>) -> String {
+        match (self, search_table_name) {
+            (TrashItemsOrder::Oldest, _) => "trash_item.id ASC".to_string(),
+            (TrashItemsOrder::Name, _) => "trash_item.name ASC, trash_item.id DESC".to_string(),
+            (TrashItemsOrder::Size, _) => {
+                "trash_item.real_size DESC, trash_item.id DESC".to_string()
+            }
+            // a match in the name counts more than a match in the path
+            (TrashItemsOrder::Relevance, Some(search_table_name)) => {
+                format!("bm25({}, 10.0, 1.0), trash_item.id DESC", search_table_name)
+            }
+            (TrashItemsOrder::Newest, _) | (TrashItemsOrder::Relevance, None) => {
+                "trash_item.id DESC".to_string()
+            }
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TrashItemsOrder::Newest => TrashItemsOrder::Oldest,
+            TrashItemsOrder::Oldest => TrashItemsOrder::Name,
+            TrashItemsOrder::Name => TrashItemsOrder::Size,
+            TrashItemsOrder::Size => TrashItemsOrder::Relevance,
+            TrashItemsOrder::Relevance => TrashItemsOrder::Newest,
+        }
+    }
+}
+
+impl std::fmt::Display for TrashItemsOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TrashItemsOrder::Newest => "newest",
+            TrashItemsOrder::Oldest => "oldest",
+            TrashItemsOrder::Name => "name",
+            TrashItemsOrder::Size => "size",
+            TrashItemsOrder::Relevance => "relevance",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Turn the words searched by the user into a full-text query: every word has to be found at the
+// beginning of a word of the name or the path, in any order. "rep 2024" finds "report_2024.pdf".
+// Return None when there is nothing to search.
+pub fn to_search_query(search: &str) -> Option<String> {
+    let terms: Vec<String> = search
+        .split_whitespace()
+        .map(|term| term.trim_end_matches('*'))
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+// The tables and the condition used to select the elements matching the query, the values are
+// added to the parameters. The full-text index is used when the query searches some words.
+fn get_query_clause(
+    is_test: bool,
+    query: &Query,
+    parameters: &mut Vec<Value>,
+) -> (String, Option<String>) {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    match to_search_query(&query.get_search()) {
+        Some(search_query) => {
+            let search_table_name = structure_manager::get_search_table_name(is_test);
+            parameters.push(Value::Text(search_query));
+            let condition = query.to_sql(parameters);
+            (
+                format!(
+                    "{0} AS trash_item JOIN {1} ON {1}.rowid = trash_item.id WHERE {1} MATCH ?1 AND {2}",
+                    table_name, search_table_name, condition
+                ),
+                Some(search_table_name),
+            )
+        }
+        None => (
+            format!(
+                "{} AS trash_item WHERE {}",
+                table_name,
+                query.to_sql(parameters)
+            ),
+            None,
+        ),
+    }
+}
+
+// Count the elements matching the query
+pub fn count_filtered_trash_items(
+    connection: &Connection,
+    is_test: bool,
+    query: &Query,
+) -> Result<usize, RmtDataBaseErrors> {
+    let mut parameters = Vec::new();
+    let (query_clause, _) = get_query_clause(is_test, query, &mut parameters);
+    connection
+        .query_row(
+            &format!("SELECT COUNT(*) FROM {}", query_clause),
+            params_from_iter(parameters),
+            |row| row.get(0),
+        )
+        .map_err(|_| RmtDataBaseErrors::CountAllElements)
+}
+
+// Get one page of the elements matching the query, only the rows of the page are read
+pub fn find_trash_items_page(
+    connection: &Connection,
+    is_test: bool,
+    query: &Query,
+    order: TrashItemsOrder,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<TrashItem>, RmtDataBaseErrors> {
+    let mut parameters = Vec::new();
+    let (query_clause, search_table_name) = get_query_clause(is_test, query, &mut parameters);
+    parameters.push(Value::Integer(limit as i64));
+    parameters.push(Value::Integer(offset as i64));
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT trash_item.* FROM {} ORDER BY {} LIMIT ?{} OFFSET ?{}",
+            query_clause,
+            order.to_sql(search_table_name.as_deref()),
+            parameters.len() - 1,
+            parameters.len()
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+
+    let items = stmt
+        .query_map(params_from_iter(parameters), |row| {
+            Ok(row_to_trash_item(row))
+        })
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+    items
+        .collect::<Result<Vec<TrashItem>, _>>()
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)
+}
+
+// Get all the elements matching the query
+pub fn find_queried_trash_items(
+    connection: &Connection,
+    is_test: bool,
+    query: &Query,
+    order: TrashItemsOrder,
+) -> Result<Vec<TrashItem>, RmtDataBaseErrors> {
+    find_trash_items_page(connection, is_test, query, order, 0, i64::MAX as usize)
+}
+
+// Get a trash item by id
 pub fn find_trash_item_by_id(
     connection: &Connection,
     is_test: bool,
@@ -158,20 +519,7 @@ pub fn find_trash_item_by_id(
     let stmt_result = connection.query_row(
         &format!("SELECT * FROM {} where id = ?1", table_name),
         [id],
-        |row| {
-            Ok(TrashItem {
-                id: get(row, 0),
-                name: get(row, 1),
-                hash: get(row, 2),
-                path: get(row, 3),
-                date: get(row, 4),
-                real_size: get(row, 5),
-                compression_size: get(row, 6),
-                is_folder: get(row, 7),
-                is_encrypted: get(row, 8),
-                is_compressed: get(row, 9),
-            })
-        },
+        |row| Ok(row_to_trash_item(row)),
     );
 
     match stmt_result {
@@ -180,6 +528,240 @@ pub fn find_trash_item_by_id(
     }
 }
 
+// Get the trash items with the given ids, or all the trash items if no id is given
+pub fn find_selected_trash_items(
+    connection: &Connection,
+    is_test: bool,
+    trash_items_ids: &[i32],
+) -> Vec<TrashItem> {
+    if trash_items_ids.is_empty() {
+        return find_all_trash_items(connection, is_test);
+    }
+    trash_items_ids
+        .iter()
+        .map(|trash_item_id| {
+            find_trash_item_by_id(connection, is_test, *trash_item_id)
+                .unwrap_or_else(|_| panic!("Failed to get item with id {}", &trash_item_id))
+        })
+        .collect()
+}
+
+// Size of an element inside the trash directory
+const STORED_SIZE_SQL: &str =
+    "CASE WHEN is_compressed THEN COALESCE(compression_size, real_size) ELSE real_size END";
+
+// Totals over all the elements of the trash, computed by the database
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TrashTotals {
+    pub element_count: usize,
+    pub original_size: u64,
+    pub stored_size: u64,
+    pub folder_count: usize,
+    pub encrypted_count: usize,
+    pub compressed_count: usize,
+}
+
+pub fn get_trash_totals(
+    connection: &Connection,
+    is_test: bool,
+) -> Result<TrashTotals, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    connection
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(real_size), 0), COALESCE(SUM({}), 0),
+                 COALESCE(SUM(is_folder), 0), COALESCE(SUM(is_encrypted), 0), COALESCE(SUM(is_compressed), 0)
+                 FROM {}",
+                STORED_SIZE_SQL, table_name
+            ),
+            [],
+            |row| {
+                Ok(TrashTotals {
+                    element_count: row.get(0)?,
+                    original_size: row.get(1)?,
+                    stored_size: row.get(2)?,
+                    folder_count: row.get(3)?,
+                    encrypted_count: row.get(4)?,
+                    compressed_count: row.get(5)?,
+                })
+            },
+        )
+        .map_err(|_| RmtDataBaseErrors::CountAllElements)
+}
+
+// The elements of the trash grouped by the directory they were trashed from
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct DirectoryVolume {
+    pub path: String,
+    pub element_count: usize,
+    pub original_size: u64,
+    pub stored_size: u64,
+}
+
+// The elements taking the most space in the trash directory
+pub fn find_largest_trash_items(
+    connection: &Connection,
+    is_test: bool,
+    limit: usize,
+) -> Result<Vec<TrashItem>, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT * FROM {} ORDER BY {} DESC, id DESC LIMIT ?1",
+            table_name, STORED_SIZE_SQL
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+
+    let items = stmt
+        .query_map([limit as i64], |row| Ok(row_to_trash_item(row)))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+    items
+        .collect::<Result<Vec<TrashItem>, _>>()
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)
+}
+
+// The directories taking the most space in the trash directory
+pub fn find_top_directories(
+    connection: &Connection,
+    is_test: bool,
+    limit: usize,
+) -> Result<Vec<DirectoryVolume>, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT path, COUNT(*), SUM(real_size), SUM({}) AS stored_size FROM {}
+             GROUP BY path ORDER BY stored_size DESC, path LIMIT ?1",
+            STORED_SIZE_SQL, table_name
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+
+    let directories = stmt
+        .query_map([limit as i64], |row| {
+            Ok(DirectoryVolume {
+                path: row.get(0)?,
+                element_count: row.get(1)?,
+                original_size: row.get(2)?,
+                stored_size: row.get(3)?,
+            })
+        })
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+    directories
+        .collect::<Result<Vec<DirectoryVolume>, _>>()
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)
+}
+
+// The elements which can be purged to free some disk space, the oldest first
+pub fn find_oldest_unpinned_trash_items(
+    connection: &Connection,
+    is_test: bool,
+) -> Result<Vec<TrashItem>, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT * FROM {} WHERE is_pinned = 0 ORDER BY date ASC, id ASC",
+            table_name
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+
+    let items = stmt
+        .query_map([], |row| Ok(row_to_trash_item(row)))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+    items
+        .collect::<Result<Vec<TrashItem>, _>>()
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)
+}
+
+// The elements trashed from the same path (directory and name), the oldest first
+pub fn find_trash_item_versions(
+    connection: &Connection,
+    is_test: bool,
+    path: &str,
+    name: &str,
+) -> Result<Vec<TrashItem>, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT * FROM {} WHERE path = ?1 AND name = ?2 ORDER BY date ASC, id ASC",
+            table_name
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+
+    let items = stmt
+        .query_map(params![path, name], |row| Ok(row_to_trash_item(row)))
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)?;
+    items
+        .collect::<Result<Vec<TrashItem>, _>>()
+        .map_err(|_| RmtDataBaseErrors::SelectAllElements)
+}
+
+// Pin or unpin an element, return false if the element doesn't exist
+pub fn set_trash_item_pinned(
+    connection: &Connection,
+    is_test: bool,
+    id: i32,
+    is_pinned: bool,
+) -> Result<bool, RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    connection
+        .execute(
+            &format!("UPDATE {} SET is_pinned = ?1 WHERE id = ?2", table_name),
+            params![is_pinned, id],
+        )
+        .map(|updated_count| updated_count > 0)
+        .map_err(|_| RmtDataBaseErrors::UpdateTrashItem(id))
+}
+
+// Save how the element is stored in the trash directory after a conversion
+pub fn update_trash_item_storage(
+    connection: &Connection,
+    is_test: bool,
+    trash_item: &TrashItem,
+) -> Result<(), RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    connection
+        .execute(
+            &format!(
+                "UPDATE {} SET compression_size = ?1, is_encrypted = ?2, is_compressed = ?3 WHERE id = ?4",
+                table_name
+            ),
+            params![
+                trash_item.compression_size,
+                trash_item.is_encrypted,
+                trash_item.is_compressed,
+                trash_item.id
+            ],
+        )
+        .map(|_| ())
+        .map_err(|_| RmtDataBaseErrors::UpdateTrashItem(trash_item.id))
+}
+
+// Save the new content of an element: its size, its checksum and the checksums of its files
+pub fn update_trash_item_content(
+    connection: &Connection,
+    is_test: bool,
+    trash_item: &TrashItem,
+    checksums: &[(String, String)],
+) -> Result<(), RmtDataBaseErrors> {
+    let table_name = structure_manager::get_data_base_table_name(is_test);
+    connection
+        .execute(
+            &format!(
+                "UPDATE {} SET real_size = ?1, compression_size = ?2, content_hash = ?3 WHERE id = ?4",
+                table_name
+            ),
+            params![
+                trash_item.real_size,
+                trash_item.compression_size,
+                trash_item.content_hash,
+                trash_item.id
+            ],
+        )
+        .map_err(|_| RmtDataBaseErrors::UpdateTrashItem(trash_item.id))?;
+    delete_checksums_by_id(connection, trash_item.id, is_test);
+    insert_checksums(connection, &trash_item.hash, checksums, is_test);
+    Ok(())
+}
+
 pub fn get_element_count(
     connection: &Connection,
     is_test: bool,
@@ -199,6 +781,7 @@ pub fn get_element_count(
 
 pub fn delete_trash_item_by_id(connection: &Connection, is_test: bool, id: i32) {
     let table_name = structure_manager::get_data_base_table_name(is_test);
+    delete_checksums_by_id(connection, id, is_test);
     connection
         .execute(
             &format!("DELETE FROM {} WHERE id = {}", table_name, id),
@@ -212,7 +795,7 @@ pub fn insert_trash_item(connection: &Connection, trash_item: &TrashItem, is_tes
 
     let stmt_result = connection
         .execute(
-            &format!("INSERT INTO {} (name, hash, path, date, real_size, compression_size, is_folder, is_encrypted, is_compressed) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)", table_name),
+            &format!("INSERT INTO {} (name, hash, path, date, real_size, compression_size, is_folder, is_encrypted, is_compressed, content_hash, is_pinned) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)", table_name),
             params![
                 trash_item.name,
                 trash_item.hash,
@@ -222,7 +805,9 @@ pub fn insert_trash_item(connection: &Connection, trash_item: &TrashItem, is_tes
                 trash_item.compression_size,
                 trash_item.is_folder,
                 trash_item.is_encrypted,
-                trash_item.is_compressed
+                trash_item.is_compressed,
+                trash_item.content_hash,
+                trash_item.is_pinned
             ],
         );
 
@@ -234,6 +819,7 @@ pub fn insert_trash_item(connection: &Connection, trash_item: &TrashItem, is_tes
 
 pub fn delete_trash_item(connection: &Connection, trash_item_id: i32, is_test: bool) {
     let table_name = structure_manager::get_data_base_table_name(is_test);
+    delete_checksums_by_id(connection, trash_item_id, is_test);
 
     let stmt_result = connection.execute(
         &format!("DELETE FROM {} WHERE id = (?1)", table_name),
@@ -255,6 +841,215 @@ pub fn delete_all_trash_item(connection: &Connection, is_test: bool) {
     connection
         .execute(&format!("DELETE FROM {}", table_name), params![])
         .unwrap();
+    connection
+        .execute(
+            &format!(
+                "DELETE FROM {}",
+                structure_manager::get_checksum_table_name(is_test)
+            ),
+            params![],
+        )
+        .unwrap();
+}
+
+pub fn insert_checksums(
+    connection: &Connection,
+    item_hash: &str,
+    checksums: &[(String, String)],
+    is_test: bool,
+) {
+    let checksum_table_name = structure_manager::get_checksum_table_name(is_test);
+
+    let stmt_result = connection.prepare(&format!(
+        "INSERT INTO {} (item_hash, inner_path, digest) VALUES (?1, ?2, ?3)",
+        checksum_table_name
+    ));
+    let mut stmt = match stmt_result {
+        Ok(stmt) => stmt,
+        Err(_) => {
+            println!("{}", RmtDataBaseErrors::InsertChecksums);
+            return;
+        }
+    };
+    for (inner_path, digest) in checksums {
+        if stmt
+            .execute(params![item_hash, inner_path, digest])
+            .is_err()
+        {
+            println!("{}", RmtDataBaseErrors::InsertChecksums);
+            return;
+        }
+    }
+}
+
+// Get the checksum of every file inside a trashed directory, sorted by path
+pub fn find_checksums(
+    connection: &Connection,
+    item_hash: &str,
+    is_test: bool,
+) -> Result<Vec<(String, String)>, RmtDataBaseErrors> {
+    let checksum_table_name = structure_manager::get_checksum_table_name(is_test);
+
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT inner_path, digest FROM {} WHERE item_hash = ?1 ORDER BY inner_path",
+            checksum_table_name
+        ))
+        .map_err(|_| RmtDataBaseErrors::SelectChecksums)?;
+    let checksums = stmt
+        .query_map([item_hash], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|_| RmtDataBaseErrors::SelectChecksums)?;
+    Ok(checksums)
+}
+
+// Save the metadata of the directories, replacing the one saved before for the same path
+pub fn save_directories_metadata(
+    connection: &Connection,
+    is_test: bool,
+    directories_metadata: &[DirectoryMetadata],
+) {
+    let directory_table_name = structure_manager::get_directory_table_name(is_test);
+
+    let stmt_result = connection.prepare(&format!(
+        "INSERT OR REPLACE INTO {} (path, mode, modified, uid, gid) VALUES (?1, ?2, ?3, ?4, ?5)",
+        directory_table_name
+    ));
+    let mut stmt = match stmt_result {
+        Ok(stmt) => stmt,
+        Err(_) => {
+            println!("{}", RmtDataBaseErrors::InsertDirectoryMetadata);
+            return;
+        }
+    };
+    for directory_metadata in directories_metadata {
+        if stmt
+            .execute(params![
+                directory_metadata.path,
+                directory_metadata.mode,
+                directory_metadata.modified,
+                directory_metadata.uid,
+                directory_metadata.gid
+            ])
+            .is_err()
+        {
+            println!("{}", RmtDataBaseErrors::InsertDirectoryMetadata);
+            return;
+        }
+    }
+}
+
+pub fn find_directory_metadata(
+    connection: &Connection,
+    is_test: bool,
+    path: &str,
+) -> Option<DirectoryMetadata> {
+    let directory_table_name = structure_manager::get_directory_table_name(is_test);
+    connection
+        .query_row(
+            &format!(
+                "SELECT path, mode, modified, uid, gid FROM {} WHERE path = ?1",
+                directory_table_name
+            ),
+            [path],
+            |row| {
+                Ok(DirectoryMetadata {
+                    path: row.get(0)?,
+                    mode: row.get(1)?,
+                    modified: row.get(2)?,
+                    uid: row.get(3)?,
+                    gid: row.get(4)?,
+                })
+            },
+        )
+        .ok()
+}
+
+fn delete_checksums_by_id(connection: &Connection, trash_item_id: i32, is_test: bool) {
+    let stmt_result = connection.execute(
+        &format!(
+            "DELETE FROM {} WHERE item_hash = (SELECT hash FROM {} WHERE id = ?1)",
+            structure_manager::get_checksum_table_name(is_test),
+            structure_manager::get_data_base_table_name(is_test)
+        ),
+        params![trash_item_id],
+    );
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DeleteElementById(trash_item_id));
+        exit(1);
+    }
+}
+
+pub fn insert_journal_entry(connection: &Connection, entry: &str, is_test: bool) -> i64 {
+    let journal_table_name = structure_manager::get_journal_table_name(is_test);
+
+    let stmt_result = connection.execute(
+        &format!("INSERT INTO {} (entry) VALUES (?1)", journal_table_name),
+        params![entry],
+    );
+
+    match stmt_result {
+        Ok(_) => connection.last_insert_rowid(),
+        Err(_) => {
+            println!("{}", RmtDataBaseErrors::InsertJournalEntry);
+            exit(1);
+        }
+    }
+}
+
+pub fn update_journal_entry(connection: &Connection, id: i64, entry: &str, is_test: bool) {
+    let journal_table_name = structure_manager::get_journal_table_name(is_test);
+
+    let stmt_result = connection.execute(
+        &format!("UPDATE {} SET entry = ?1 WHERE id = ?2", journal_table_name),
+        params![entry, id],
+    );
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::InsertJournalEntry);
+        exit(1);
+    }
+}
+
+pub fn delete_journal_entry(connection: &Connection, id: i64, is_test: bool) {
+    let journal_table_name = structure_manager::get_journal_table_name(is_test);
+
+    let stmt_result = connection.execute(
+        &format!("DELETE FROM {} WHERE id = ?1", journal_table_name),
+        params![id],
+    );
+
+    if stmt_result.is_err() {
+        println!("{}", RmtDataBaseErrors::DeleteJournalEntry(id));
+        exit(1);
+    }
+}
+
+pub fn find_all_journal_entries(connection: &Connection, is_test: bool) -> Vec<(i64, String)> {
+    let journal_table_name = structure_manager::get_journal_table_name(is_test);
+
+    let mut stmt = match connection.prepare(&format!(
+        "SELECT id, entry FROM {} ORDER BY id",
+        journal_table_name
+    )) {
+        Ok(stmt) => stmt,
+        Err(_) => {
+            println!("{}", RmtDataBaseErrors::SelectAllElements);
+            exit(1);
+        }
+    };
+
+    let entries = stmt
+        .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect());
+    match entries {
+        Ok(entries) => entries,
+        Err(_) => {
+            println!("{}", RmtDataBaseErrors::SelectAllElements);
+            exit(1);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -271,7 +1066,7 @@ mod tests {
             "Amine".to_string(),
             "test".to_string(),
             "home/user".to_string(),
-            "00::00::01".to_string(),
+            0,
             10,
             None,
             false,
@@ -303,7 +1098,7 @@ mod tests {
             "Amine".to_string(),
             "Unique".to_string(),
             "home/user".to_string(),
-            "00::00::01".to_string(),
+            0,
             10,
             Some(4),
             false,
@@ -334,7 +1129,7 @@ mod tests {
             "Amine".to_string(),
             "Unique1".to_string(),
             "home/user".to_string(),
-            "00::00::01".to_string(),
+            0,
             10,
             None,
             false,
@@ -346,7 +1141,7 @@ mod tests {
             "Amine".to_string(),
             "Unique2".to_string(),
             "home/user".to_string(),
-            "00::00::01".to_string(),
+            0,
             10,
             Some(4),
             false,
@@ -381,7 +1176,7 @@ mod tests {
             "Amine".to_string(),
             "Unique1".to_string(),
             "home/user".to_string(),
-            "00::00::01".to_string(),
+            0,
             10,
             None,
             false,
@@ -403,6 +1198,107 @@ mod tests {
         structure_manager::clear_structure(is_test);
     }
 
+    #[test]
+    fn test_insert_checksums() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let mut trash_item = TrashItem::new(
+            "folder".to_string(),
+            "Unique1".to_string(),
+            "home/user".to_string(),
+            0,
+            10,
+            None,
+            true,
+            false,
+            false,
+        );
+        trash_item.content_hash = Some("manifest".to_string());
+        let checksums = vec![
+            ("a.txt".to_string(), "digest_a".to_string()),
+            ("src/b.rs".to_string(), "digest_b".to_string()),
+        ];
+
+        insert_trash_item(&connection, &trash_item, is_test);
+        insert_checksums(&connection, &trash_item.hash, &checksums, is_test);
+
+        let trash_items = find_all_trash_items(&connection, is_test);
+        assert_eq!(trash_items[0].content_hash, trash_item.content_hash);
+        assert_eq!(
+            find_checksums(&connection, &trash_item.hash, is_test).unwrap(),
+            checksums
+        );
+
+        delete_trash_item(&connection, trash_items[0].id, is_test);
+        assert!(find_checksums(&connection, &trash_item.hash, is_test)
+            .unwrap()
+            .is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_add_missing_columns_to_existing_database() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+        let connection = structure_manager::create_data_base_file(is_test);
+        let table_name = structure_manager::get_data_base_table_name(is_test);
+        // table created by the first versions of rmt
+        connection
+            .execute_batch(&format!(
+                "DROP TABLE {0};
+                DROP TABLE {1};
+                CREATE TABLE {0} (
+                 id INTEGER PRIMARY KEY,
+                 name TEXT NOT NULL,
+                 hash NOT NULL UNIQUE,
+                 path TEXT NOT NULL,
+                 date TEXT NOT NULL,
+                 real_size INTEGER NOT NULL,
+                 compression_size INTEGER,
+                 is_folder INTEGER NOT NULL,
+                 is_encrypted INTEGER NOT NULL
+                );
+                INSERT INTO {0} (name, hash, path, date, real_size, is_folder, is_encrypted)
+                 VALUES ('old.txt', 'old', '/home/user', '2023-04-01 18:30:00', 10, 0, 0);",
+                table_name,
+                structure_manager::get_search_table_name(is_test)
+            ))
+            .unwrap();
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+
+        let connection = create_database(is_test);
+        assert_eq!(
+            get_column_count(&connection, is_test),
+            TrashItem::field_count()
+        );
+        let trash_items = find_all_trash_items(&connection, is_test);
+        let local_date =
+            chrono::NaiveDateTime::parse_from_str("2023-04-01 18:30:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .and_local_timezone(chrono::Local)
+                .unwrap();
+        assert_eq!(trash_items[0].date, local_date.timestamp());
+        assert!(!trash_items[0].is_compressed);
+        // the full-text index still follows the trash table
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("old").unwrap())
+                .unwrap(),
+            1
+        );
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
     #[test]
     fn test_add_is_compressed_column_to_existing_database() {
         let is_test = false;
@@ -418,4 +1314,265 @@ mod tests {
             .expect("Unable to close sqlite connection");
         structure_manager::clear_structure(is_test)
     }
+
+    #[test]
+    fn test_get_trash_totals() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let trash_items = [
+            ("a.txt", "/home/user/docs", 1200, None, false, false, false),
+            ("b", "/home/user/docs", 3000, Some(1000), true, true, true),
+            ("c.log", "/var/log", 500, Some(400), false, false, true),
+        ];
+        for (
+            i,
+            (name, path, real_size, compression_size, is_folder, is_encrypted, is_compressed),
+        ) in trash_items.into_iter().enumerate()
+        {
+            let trash_item = TrashItem::new(
+                name.to_string(),
+                format!("totals{}", i),
+                path.to_string(),
+                0,
+                real_size,
+                compression_size,
+                is_folder,
+                is_encrypted,
+                is_compressed,
+            );
+            insert_trash_item(&connection, &trash_item, is_test);
+        }
+
+        assert_eq!(
+            get_trash_totals(&connection, is_test).unwrap(),
+            TrashTotals {
+                element_count: 3,
+                original_size: 4700,
+                stored_size: 2600,
+                folder_count: 1,
+                encrypted_count: 1,
+                compressed_count: 2,
+            }
+        );
+        assert_eq!(
+            find_top_directories(&connection, is_test, 1).unwrap(),
+            vec![DirectoryVolume {
+                path: "/home/user/docs".to_string(),
+                element_count: 2,
+                original_size: 4200,
+                stored_size: 2200,
+            }]
+        );
+        let largest_names: Vec<String> = find_largest_trash_items(&connection, is_test, 2)
+            .unwrap()
+            .into_iter()
+            .map(|trash_item| trash_item.name)
+            .collect();
+        assert_eq!(largest_names, vec!["a.txt", "b"]);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_find_trash_items_page() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        for i in 0..25 {
+            let trash_item = TrashItem::new(
+                format!("{}{}", if i % 2 == 0 { "even" } else { "odd" }, i),
+                format!("page{}", i),
+                "home/user".to_string(),
+                0,
+                i,
+                None,
+                false,
+                false,
+                false,
+            );
+            insert_trash_item(&connection, &trash_item, is_test);
+        }
+
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("").unwrap()).unwrap(),
+            25
+        );
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("even").unwrap())
+                .unwrap(),
+            13
+        );
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("EVEN").unwrap())
+                .unwrap(),
+            13
+        );
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("even user").unwrap())
+                .unwrap(),
+            13
+        );
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("even2*").unwrap())
+                .unwrap(),
+            4
+        );
+
+        let page = find_trash_items_page(
+            &connection,
+            is_test,
+            &Query::parse("").unwrap(),
+            TrashItemsOrder::Newest,
+            20,
+            10,
+        )
+        .unwrap();
+        assert_eq!(page.len(), 5);
+        assert_eq!(page[0].name, "even4");
+
+        let page = find_trash_items_page(
+            &connection,
+            is_test,
+            &Query::parse("odd").unwrap(),
+            TrashItemsOrder::Size,
+            0,
+            3,
+        )
+        .unwrap();
+        let sizes: Vec<u64> = page.iter().map(|trash_item| trash_item.real_size).collect();
+        assert_eq!(sizes, vec![23, 21, 19]);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_search_trash_items() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        for (name, path) in [
+            ("report_2024.pdf", "/home/user/work"),
+            ("work_notes.txt", "/home/user/docs"),
+            ("photo.png", "/home/user/pictures"),
+        ] {
+            let trash_item = TrashItem::new(
+                name.to_string(),
+                sha256::digest(name),
+                path.to_string(),
+                0,
+                1,
+                None,
+                false,
+                false,
+                false,
+            );
+            insert_trash_item(&connection, &trash_item, is_test);
+        }
+
+        let page = find_trash_items_page(
+            &connection,
+            is_test,
+            &Query::parse("work").unwrap(),
+            TrashItemsOrder::Relevance,
+            0,
+            10,
+        )
+        .unwrap();
+        let names: Vec<&str> = page.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["work_notes.txt", "report_2024.pdf"]);
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("rep 2024").unwrap())
+                .unwrap(),
+            1
+        );
+
+        // the index follows the changes of the trash table
+        delete_trash_item(&connection, page[0].id, is_test);
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("work").unwrap())
+                .unwrap(),
+            1
+        );
+
+        // the elements trashed before the creation of the index are indexed
+        connection
+            .execute_batch(&format!(
+                "DROP TABLE {}",
+                structure_manager::get_search_table_name(is_test)
+            ))
+            .unwrap();
+        let connection = create_database(is_test);
+        assert_eq!(
+            count_filtered_trash_items(&connection, is_test, &Query::parse("photo").unwrap())
+                .unwrap(),
+            1
+        );
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_find_queried_trash_items() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let now = chrono::offset::Utc::now();
+        for (name, path, size, is_folder, date) in [
+            ("app.log", "/home/user/work", 200 << 20, false, now),
+            ("src", "/home/user/work/project", 300 << 20, true, now),
+            (
+                "old.log",
+                "/home/user/workshop",
+                10,
+                false,
+                now - chrono::Duration::days(30),
+            ),
+        ] {
+            let trash_item = TrashItem::new(
+                name.to_string(),
+                sha256::digest(name),
+                path.to_string(),
+                date.timestamp(),
+                size,
+                None,
+                is_folder,
+                false,
+                false,
+            );
+            insert_trash_item(&connection, &trash_item, is_test);
+        }
+
+        let find_names = |query: &str| -> Vec<String> {
+            find_queried_trash_items(
+                &connection,
+                is_test,
+                &Query::parse(query).unwrap(),
+                TrashItemsOrder::Name,
+            )
+            .unwrap()
+            .into_iter()
+            .map(|trash_item| trash_item.name)
+            .collect()
+        };
+        assert_eq!(find_names("path:/home/user/work"), vec!["app.log", "src"]);
+        assert_eq!(find_names("size>100M type:file"), vec!["app.log"]);
+        assert_eq!(find_names("name:*.log age<7d"), vec!["app.log"]);
+        assert_eq!(find_names("age>=7d"), vec!["old.log"]);
+        assert_eq!(find_names("log size<1K"), vec!["old.log"]);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
 }
@@ -13,6 +13,12 @@ pub enum RmtDataBaseErrors {
     CountAllElements,
     InsertTrashItem,
     DeleteAllElement,
+    InsertChecksums,
+    SelectChecksums,
+    InsertJournalEntry,
+    DeleteJournalEntry(i64),
+    UpdateTrashItem(i32),
+    InsertDirectoryMetadata,
 }
 
 impl RmtDataBaseErrors {
@@ -34,6 +40,22 @@ impl RmtDataBaseErrors {
             RmtDataBaseErrors::CountAllElements => "Failed to count all the elements".to_string(),
             RmtDataBaseErrors::InsertTrashItem => "Impossible to insert the trashItem.".to_string(),
             RmtDataBaseErrors::DeleteAllElement => "Impossible to delete all elements.".to_string(),
+            RmtDataBaseErrors::InsertChecksums => "Impossible to insert the checksums.".to_string(),
+            RmtDataBaseErrors::SelectChecksums => "Impossible to select the checksums.".to_string(),
+            RmtDataBaseErrors::InsertJournalEntry => {
+                "Impossible to save the operation in the journal.".to_string()
+            }
+            RmtDataBaseErrors::DeleteJournalEntry(id) => format!(
+                "Impossible to delete the journal entry at index {}.",
+                id.to_string().red().bold()
+            ),
+            RmtDataBaseErrors::InsertDirectoryMetadata => {
+                "Impossible to save the metadata of the parent directories.".to_string()
+            }
+            RmtDataBaseErrors::UpdateTrashItem(id) => format!(
+                "Impossible to update the element at index {}.",
+                id.to_string().red().bold()
+            ),
         }
     }
 
@@ -0,0 +1,528 @@
+// Compare an element of the trash with a file or a directory on the disk (rmt --diff), by default
+// the one now at its original path. Two text files are compared line by line and shown as a
+// unified diff. Two directories are compared with the checksums of their files, saved when the
+// directory was trashed, so the stored directory doesn't even need to be decoded.
+
+use crate::checksum_manager::{compute_element_checksums, compute_stored_checksums};
+use crate::data_manager;
+use crate::inspect_manager::{self, ElementPath, StoredElement};
+use crate::trash_item::TrashItem;
+
+use colored::Colorize;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, MAIN_SEPARATOR_STR};
+
+// Lines shown around the changed lines
+const CONTEXT_LINES: usize = 3;
+// Beyond this number of changed lines, the memory needed grows too much and the diff wouldn't be
+// readable anyway, the files are only said to differ
+const MAX_EDITS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal(usize, usize), // index of the line in the old and in the new text
+    Delete(usize),       // index of the line in the old text
+    Insert(usize),       // index of the line in the new text
+}
+
+// Shortest list of edits turning the old lines into the new ones (Myers' algorithm), None if it
+// has more than MAX_EDITS insertions and deletions
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Option<Vec<Edit>> {
+    let (old_len, new_len) = (old.len() as isize, new.len() as isize);
+    let max = (old_len + new_len) as usize;
+    let offset = max as isize;
+    // furthest x reached on each diagonal k = x - y
+    let mut furthest = vec![0isize; 2 * max + 2];
+    // the diagonals -d..=d + 1 of furthest saved before each round d, for the backtracking
+    let mut trace = Vec::new();
+    // the furthest values start at the diagonal first_k
+    let is_down = |furthest: &[isize], first_k: isize, k: isize, d: isize| {
+        let at = |k: isize| furthest[(k - first_k) as usize];
+        k == -d || (k != d && at(k - 1) < at(k + 1))
+    };
+
+    'search: for d in 0..=max as isize {
+        if d as usize > MAX_EDITS {
+            return None;
+        }
+        trace.push(furthest[(offset - d) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if is_down(&furthest, -offset, k, d) {
+                furthest[(k + 1 + offset) as usize]
+            } else {
+                furthest[(k - 1 + offset) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < old_len && y < new_len && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[(k + offset) as usize] = x;
+            if x >= old_len && y >= new_len {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (old_len, new_len);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous_k = if is_down(furthest, -d, k, d) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest[(previous_k + d) as usize];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Equal((x - 1) as usize, (y - 1) as usize));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Insert((y - 1) as usize));
+            } else {
+                edits.push(Edit::Delete((x - 1) as usize));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+// "@@ -3,7 +3,8 @@", an empty range starts at the line before it
+fn format_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+// The changed lines with some context, in the unified format of diff -u
+pub fn format_unified_diff(old: &[&str], new: &[&str], edits: &[Edit]) -> Vec<String> {
+    // the number of old and new lines before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_position, mut new_position) = (0, 0);
+    for edit in edits {
+        positions.push((old_position, new_position));
+        match edit {
+            Edit::Equal(_, _) => {
+                old_position += 1;
+                new_position += 1;
+            }
+            Edit::Delete(_) => old_position += 1,
+            Edit::Insert(_) => new_position += 1,
+        }
+    }
+
+    // the hunks are the changes with their context, merged when they overlap
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Equal(_, _)) {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+        let (old_start, new_start) = positions[start];
+        lines.push(
+            format!(
+                "@@ -{} +{} @@",
+                format_range(old_start, old_count),
+                format_range(new_start, new_count)
+            )
+            .cyan()
+            .to_string(),
+        );
+        for edit in hunk {
+            lines.push(match edit {
+                Edit::Equal(old_index, _) => format!(" {}", old[*old_index]),
+                Edit::Delete(old_index) => format!("-{}", old[*old_index]).red().to_string(),
+                Edit::Insert(new_index) => format!("+{}", new[*new_index]).green().to_string(),
+            });
+        }
+    }
+    lines
+}
+
+// The text of a file, None for a binary file
+fn to_text(content: &[u8]) -> Option<&str> {
+    if content.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(content).ok()
+}
+
+// Compare the content of the trashed file with the file on the disk, return true if they are equal
+fn diff_files(trashed_name: &str, trashed_content: &[u8], path: &str) -> io::Result<bool> {
+    let content = fs::read(path)?;
+    if content == trashed_content {
+        println!("{} and {} are identical.", trashed_name.bold(), path.bold());
+        return Ok(true);
+    }
+    match (to_text(trashed_content), to_text(&content)) {
+        (Some(trashed_text), Some(text)) => {
+            let trashed_lines: Vec<&str> = trashed_text.lines().collect();
+            let lines: Vec<&str> = text.lines().collect();
+            let edits = match diff_lines(&trashed_lines, &lines) {
+                Some(edits) => edits,
+                None => {
+                    println!(
+                        "Files {} and {} differ on more than {} lines.",
+                        trashed_name.bold(),
+                        path.bold(),
+                        MAX_EDITS
+                    );
+                    return Ok(false);
+                }
+            };
+            println!("{}", format!("--- {} (trash)", trashed_name).bold());
+            println!("{}", format!("+++ {}", path).bold());
+            format_unified_diff(&trashed_lines, &lines, &edits)
+                .iter()
+                .for_each(|line| println!("{}", line));
+            // only the end of line of the last line differs
+            if edits.iter().all(|edit| matches!(edit, Edit::Equal(_, _))) {
+                println!("\\ The files only differ by their end of lines.");
+            }
+        }
+        _ => println!(
+            "Binary files {} and {} differ.",
+            trashed_name.bold(),
+            path.bold()
+        ),
+    }
+    Ok(false)
+}
+
+// The files of a directory that were added, removed or changed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DirectoryDifferences {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl DirectoryDifferences {
+    pub fn new(trashed_files: &[(String, String)], files: &[(String, String)]) -> Self {
+        let mut differences = Self::default();
+        let trashed_files: BTreeMap<&String, &String> = trashed_files
+            .iter()
+            .map(|(path, digest)| (path, digest))
+            .collect();
+        let files: BTreeMap<&String, &String> =
+            files.iter().map(|(path, digest)| (path, digest)).collect();
+        for (inner_path, digest) in &trashed_files {
+            match files.get(inner_path) {
+                None => differences.removed.push(inner_path.to_string()),
+                Some(new_digest) if new_digest != digest => {
+                    differences.changed.push(inner_path.to_string())
+                }
+                Some(_) => (),
+            }
+        }
+        differences.added = files
+            .keys()
+            .filter(|inner_path| !trashed_files.contains_key(*inner_path))
+            .map(|inner_path| inner_path.to_string())
+            .collect();
+        differences
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+// The checksums of the files of the trashed directory (or of one of its directories)
+fn find_trashed_files(
+    connection: &Connection,
+    trash_item: &TrashItem,
+    inner_path: &str,
+    is_test: bool,
+) -> io::Result<Vec<(String, String)>> {
+    let files = match data_manager::find_checksums(connection, &trash_item.hash, is_test) {
+        Ok(files) if trash_item.content_hash.is_some() => files,
+        // trashed by an older version of rmt, the checksums are computed from the trash
+        _ => compute_stored_checksums(trash_item, is_test)?.files,
+    };
+    if inner_path.is_empty() {
+        return Ok(files);
+    }
+    let prefix = format!("{}/", inner_path);
+    Ok(files
+        .into_iter()
+        .filter_map(|(path, digest)| {
+            path.strip_prefix(&prefix)
+                .map(|path| (path.to_string(), digest))
+        })
+        .collect())
+}
+
+fn diff_directories(
+    trashed_name: &str,
+    trashed_files: &[(String, String)],
+    path: &str,
+) -> io::Result<bool> {
+    let differences =
+        DirectoryDifferences::new(trashed_files, &compute_element_checksums(path)?.files);
+    if differences.is_empty() {
+        println!("{} and {} are identical.", trashed_name.bold(), path.bold());
+        return Ok(true);
+    }
+    differences
+        .added
+        .iter()
+        .for_each(|inner_path| println!("{} {}", "+".green().bold(), inner_path.green()));
+    differences
+        .removed
+        .iter()
+        .for_each(|inner_path| println!("{} {}", "-".red().bold(), inner_path.red()));
+    differences
+        .changed
+        .iter()
+        .for_each(|inner_path| println!("{} {}", "~".yellow().bold(), inner_path.yellow()));
+    println!(
+        "{} added, {} removed and {} changed in {} since {} was trashed.",
+        differences.added.len(),
+        differences.removed.len(),
+        differences.changed.len(),
+        path.bold(),
+        trashed_name.bold()
+    );
+    Ok(false)
+}
+
+// Compare the element (or a path inside it) with the path on the disk, by default its original
+// path. Like diff, return false when they differ or can't be compared.
+pub fn diff_element(
+    connection: &Connection,
+    is_test: bool,
+    element_path: &ElementPath,
+    path: Option<&str>,
+) -> bool {
+    let trash_item = match data_manager::find_trash_item_by_id(connection, is_test, element_path.id)
+    {
+        Ok(trash_item) => trash_item,
+        Err(_) => {
+            println!(
+                "{}: there is no element with the id {} in the trash.",
+                "Error".red().bold(),
+                element_path.id.to_string().red().bold()
+            );
+            return false;
+        }
+    };
+    let trashed_name = if element_path.inner_path.is_empty() {
+        trash_item.name.clone()
+    } else {
+        format!("{}/{}", trash_item.name, element_path.inner_path)
+    };
+    let path = match path {
+        Some(path) => crate::restore_manager::to_absolute_path(path),
+        None => [trash_item.path.as_str(), trash_item.name.as_str()]
+            .into_iter()
+            .chain(
+                element_path
+                    .inner_path
+                    .split('/')
+                    .filter(|name| !name.is_empty()),
+            )
+            .collect::<Vec<&str>>()
+            .join(MAIN_SEPARATOR_STR),
+    };
+    if !Path::new(&path).exists() {
+        println!(
+            "{}: {} doesn't exist.",
+            "Error".red().bold(),
+            path.red().bold()
+        );
+        return false;
+    }
+
+    let result =
+        inspect_manager::get_encryption_key(&trash_item, is_test).and_then(|encryption_key| {
+            let mut stored_element =
+                StoredElement::open(&trash_item, encryption_key.as_ref(), is_test)?;
+            let is_trashed_directory = stored_element.entries().map(|entries| {
+                trash_item.is_folder
+                    && (element_path.inner_path.is_empty()
+                        || entries.iter().any(|entry| {
+                            entry.is_dir && entry.inner_path == element_path.inner_path
+                        }))
+            })?;
+            match (is_trashed_directory, Path::new(&path).is_dir()) {
+                (true, true) => diff_directories(
+                    &trashed_name,
+                    &find_trashed_files(
+                        connection,
+                        &trash_item,
+                        &element_path.inner_path,
+                        is_test,
+                    )?,
+                    &path,
+                ),
+                (false, false) => {
+                    let mut trashed_content = Vec::new();
+                    stored_element
+                        .open_file(&element_path.inner_path)?
+                        .read_to_end(&mut trashed_content)?;
+                    diff_files(&trashed_name, &trashed_content, &path)
+                }
+                (true, false) => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is a directory and {} is a file", trashed_name, path),
+                )),
+                (false, true) => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is a file and {} is a directory", trashed_name, path),
+                )),
+            }
+        });
+    match result {
+        Ok(is_identical) => is_identical,
+        Err(e) => {
+            println!("{}: {}.", "Error".red().bold(), e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_edits(old: &[&str], new: &[&str], edits: &[Edit]) -> (Vec<String>, Vec<String>) {
+        let mut rebuilt_old = Vec::new();
+        let mut rebuilt_new = Vec::new();
+        for edit in edits {
+            match edit {
+                Edit::Equal(old_index, new_index) => {
+                    assert_eq!(old[*old_index], new[*new_index]);
+                    rebuilt_old.push(old[*old_index].to_string());
+                    rebuilt_new.push(new[*new_index].to_string());
+                }
+                Edit::Delete(old_index) => rebuilt_old.push(old[*old_index].to_string()),
+                Edit::Insert(new_index) => rebuilt_new.push(new[*new_index].to_string()),
+            }
+        }
+        (rebuilt_old, rebuilt_new)
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = ["a", "b", "c", "a", "b", "b", "a"];
+        let new = ["c", "b", "a", "b", "a", "c"];
+        let edits = diff_lines(&old, &new).unwrap();
+        let (rebuilt_old, rebuilt_new) = apply_edits(&old, &new, &edits);
+        assert_eq!(rebuilt_old, old);
+        assert_eq!(rebuilt_new, new);
+        // the shortest edit script of this example has 5 edits
+        assert_eq!(
+            edits
+                .iter()
+                .filter(|edit| !matches!(edit, Edit::Equal(_, _)))
+                .count(),
+            5
+        );
+
+        assert_eq!(diff_lines::<&str>(&[], &[]), Some(Vec::new()));
+        assert_eq!(diff_lines(&[], &["a"]), Some(vec![Edit::Insert(0)]));
+        assert_eq!(diff_lines(&["a"], &[]), Some(vec![Edit::Delete(0)]));
+    }
+
+    #[test]
+    fn test_diff_large_lines() {
+        let lines: Vec<String> = (0..200_000).map(|line| line.to_string()).collect();
+        let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+
+        // a few changes in a long file are found
+        let old = &lines[..100_000];
+        let mut new = old.to_vec();
+        new[10] = "ten";
+        new.remove(50_000);
+        let edits = diff_lines(old, &new).unwrap();
+        let (rebuilt_old, rebuilt_new) = apply_edits(old, &new, &edits);
+        assert_eq!(rebuilt_old, old);
+        assert_eq!(rebuilt_new, new);
+
+        // two long files without any line in common
+        assert_eq!(diff_lines(old, &lines[100_000..]), None);
+    }
+
+    #[test]
+    fn test_format_unified_diff() {
+        colored::control::set_override(false);
+        let old: Vec<String> = (1..=10).map(|line| line.to_string()).collect();
+        let old: Vec<&str> = old.iter().map(|line| line.as_str()).collect();
+        let mut new = old.clone();
+        new[4] = "five";
+        new.push("11");
+        let lines = format_unified_diff(&old, &new, &diff_lines(&old, &new).unwrap());
+        colored::control::unset_override();
+        assert_eq!(
+            lines,
+            vec![
+                "@@ -2,9 +2,10 @@",
+                " 2",
+                " 3",
+                " 4",
+                "-5",
+                "+five",
+                " 6",
+                " 7",
+                " 8",
+                " 9",
+                " 10",
+                "+11"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directory_differences() {
+        let to_files = |files: &[(&str, &str)]| -> Vec<(String, String)> {
+            files
+                .iter()
+                .map(|(path, digest)| (path.to_string(), digest.to_string()))
+                .collect()
+        };
+        let differences = DirectoryDifferences::new(
+            &to_files(&[("a.txt", "1"), ("b.txt", "2"), ("c.txt", "3")]),
+            &to_files(&[("a.txt", "1"), ("c.txt", "4"), ("d.txt", "5")]),
+        );
+        assert_eq!(
+            differences,
+            DirectoryDifferences {
+                added: vec!["d.txt".to_string()],
+                removed: vec!["b.txt".to_string()],
+                changed: vec!["c.txt".to_string()],
+            }
+        );
+    }
+}
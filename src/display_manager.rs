@@ -1,4 +1,6 @@
-use crate::{data_manager, trash_item::TrashItem};
+use crate::config::DateFormat;
+use crate::data_manager::{self, TrashItemsOrder};
+use crate::query_manager::Query;
 use colored::{ColoredString, Colorize};
 use rusqlite::Connection;
 
@@ -12,11 +14,13 @@ pub struct DisplayInfos {
     pub max_element_per_page: usize,
     pub total_page: f64, // Should not be f64 but usize, but ceiling problems
     pub filter: Filter,
+    pub order: TrashItemsOrder,
+    pub date_format: DateFormat,
     pub selected_trash_items: SelectedTrashItems,
 }
 
 impl DisplayInfos {
-    pub fn new(total_elements: usize) -> Self {
+    pub fn new(total_elements: usize, date_format: DateFormat) -> Self {
         Self {
             current_cursor_index: 0,
             current_page: 1,
@@ -27,6 +31,8 @@ impl DisplayInfos {
                 is_filter: false,
                 content: String::new(),
             },
+            order: TrashItemsOrder::default(),
+            date_format,
             selected_trash_items: SelectedTrashItems {
                 restore: Vec::<i32>::new(),
                 delete: Vec::<i32>::new(),
@@ -46,13 +52,12 @@ pub struct Filter {
 }
 
 impl Filter {
-    pub fn is_valid_item(&self, trash_item: &TrashItem) -> bool {
-        if !self.is_filter {
-            true
+    // The query selecting the elements (see query_manager), empty to keep everything
+    pub fn get_content(&self) -> &str {
+        if self.is_filter {
+            &self.content
         } else {
-            trash_item.name.contains(&self.content)
-                || trash_item.date.contains(&self.content)
-                || trash_item.path.contains(&self.content)
+            ""
         }
     }
 }
@@ -64,36 +69,54 @@ pub fn display_trash(
 ) -> i32 {
     println!("Which elements do you want to restore ?\n\r");
 
-    // Getting all trash item from ddb
-    let mut trash_items = data_manager::find_all_trash_items(connection, is_test);
-    // filter item if the "filter mode" is activate
-    trash_items.retain(|item| display_infos.filter.is_valid_item(item));
-    display_infos.total_elements = trash_items.len();
+    // only the elements of the current page are read from the database, and nothing is displayed
+    // while the query is not valid
+    let query = Query::parse(display_infos.filter.get_content());
+    display_infos.total_elements = match &query {
+        Ok(query) => data_manager::count_filtered_trash_items(connection, is_test, query)
+            .expect("Failed to count the elements of the trash"),
+        Err(_) => 0,
+    };
+    display_infos.total_page =
+        (display_infos.total_elements as f64 / display_infos.max_element_per_page as f64).ceil();
 
     let starting_index = (display_infos.current_page - 1) * display_infos.max_element_per_page;
-    let end_index = (display_infos.current_page) * display_infos.max_element_per_page;
+    let trash_items = match &query {
+        Ok(query) => data_manager::find_trash_items_page(
+            connection,
+            is_test,
+            query,
+            display_infos.order,
+            starting_index,
+            display_infos.max_element_per_page,
+        )
+        .expect("Failed to get the elements of the trash"),
+        Err(_) => Vec::new(),
+    };
 
     let mut current_selected_id = 0;
 
-    for i in starting_index..end_index {
-        if i >= trash_items.len() {
-            continue;
-        }
-        let trash_item_str = format!("{} ➜ {}", i, trash_items[i]).white();
+    for (i, trash_item) in (starting_index..).zip(trash_items.iter()) {
+        let trash_item_str = format!(
+            "{} ➜ {}",
+            i,
+            trash_item.display_with(display_infos.date_format)
+        )
+        .white();
         let is_restore = display_infos
             .selected_trash_items
             .restore
-            .contains(&trash_items[i].id);
+            .contains(&trash_item.id);
         let is_delete = display_infos
             .selected_trash_items
             .delete
-            .contains(&trash_items[i].id);
+            .contains(&trash_item.id);
         let display_element = get_color_display_element(is_restore, is_delete, &trash_item_str);
 
         if i == display_infos.current_cursor_index || is_restore || is_delete {
             if i == display_infos.current_cursor_index {
                 print!("{}", ">".green().bold());
-                current_selected_id = trash_items[i].id;
+                current_selected_id = trash_item.id;
             }
             print!("    ");
         }
@@ -109,8 +132,12 @@ pub fn display_trash(
             "Filter".green().bold(),
             display_infos.filter.content
         );
+        if let Err(e) = &query {
+            println!("{}\r", e.error_message());
+        }
         println!("\r")
     }
+    println!("{}: {}\r", "Sort".green().bold(), display_infos.order);
     display_inputs_commands(display_infos.filter.is_filter);
 
     current_selected_id
@@ -132,10 +159,11 @@ fn display_pages_marks(display_infos: &DisplayInfos) {
 fn display_inputs_commands(is_filter: bool) {
     if !is_filter {
         println!(
-            "{} • {} • {} • {} • {} • {} • {} • {}\r",
+            "{} • {} • {} • {} • {} • {} • {} • {} • {}\r",
             display_input("↑/k", "up"),
             display_input("↓/j", "down"),
             display_input("esc", "filter"),
+            display_input("s", "sort"),
             display_input("ctrl(d)", "clear filter"),
             display_input("space", "restore"),
             display_input("del/f", "flush"),
@@ -170,6 +198,68 @@ fn get_color_display_element(is_restore: bool, is_delete: bool, text: &str) -> C
     }
 }
 
+// IEC units are powers of 1024 (KiB, MiB...), SI units are powers of 1000 (kB, MB...)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SizeUnits {
+    #[default]
+    Iec,
+    Si,
+}
+
+// 1536 -> "1.5 KiB" (IEC) or "1.5 kB" (SI)
+pub fn format_size(size: u64, size_units: SizeUnits) -> String {
+    let (base, units) = match size_units {
+        SizeUnits::Iec => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB"]),
+        SizeUnits::Si => (1000.0, ["B", "kB", "MB", "GB", "TB", "PB"]),
+    };
+    let mut value = size as f64;
+    let mut unit_index = 0;
+    while value >= base && unit_index < units.len() - 1 {
+        value /= base;
+        unit_index += 1;
+    }
+    if unit_index == 0 {
+        format!("{} {}", size, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit_index])
+    }
+}
+
+// Ask the user to pick one of the choices, the last choice is used if the answer is not valid
+pub fn get_user_choice(message: &str, choices: &[&str]) -> usize {
+    let mut user_input = String::new();
+    println!("{}", message);
+    choices.iter().enumerate().for_each(|(index, choice)| {
+        println!("  {} {}", format!("[{}]", index + 1).green().bold(), choice)
+    });
+    std::io::stdin().read_line(&mut user_input).unwrap();
+    match user_input.trim().parse::<usize>() {
+        Ok(choice) if choice >= 1 && choice <= choices.len() => choice - 1,
+        _ => choices.len() - 1,
+    }
+}
+
+// Ask the user to pick some of the numbered entries, "1 3" or "1,3". The numbers out of range
+// are ignored, nothing is selected if the answer is empty.
+pub fn get_user_selection(message: &str, entry_count: usize) -> Vec<usize> {
+    let mut user_input = String::new();
+    println!("{}", message);
+    std::io::stdin().read_line(&mut user_input).unwrap();
+    parse_selection(&user_input, entry_count)
+}
+
+fn parse_selection(user_input: &str, entry_count: usize) -> Vec<usize> {
+    let mut selection: Vec<usize> = user_input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|number| number.parse::<usize>().ok())
+        .filter(|number| *number >= 1 && *number <= entry_count)
+        .map(|number| number - 1)
+        .collect();
+    selection.sort_unstable();
+    selection.dedup();
+    selection
+}
+
 pub fn get_user_validation(message: &str) -> bool {
     let mut user_input = String::new();
     println!("{} {}", message, "[y/n]".green().bold());
@@ -177,3 +267,25 @@ pub fn get_user_validation(message: &str) -> bool {
     user_input.pop();
     user_input == "y" || user_input == "yes"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0, SizeUnits::Iec), "0 B");
+        assert_eq!(format_size(1023, SizeUnits::Iec), "1023 B");
+        assert_eq!(format_size(1536, SizeUnits::Iec), "1.5 KiB");
+        assert_eq!(format_size(1536, SizeUnits::Si), "1.5 kB");
+        assert_eq!(format_size(100 << 20, SizeUnits::Iec), "100.0 MiB");
+        assert_eq!(format_size(3_200_000_000, SizeUnits::Si), "3.2 GB");
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("3 1,2\n", 5), vec![0, 1, 2]);
+        assert_eq!(parse_selection("2 2 0 9 x\n", 5), vec![1]);
+        assert!(parse_selection("\n", 5).is_empty());
+    }
+}
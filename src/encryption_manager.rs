@@ -0,0 +1,364 @@
+use crate::agent_manager;
+
+use chacha20poly1305::{aead::stream, KeyInit, XChaCha20Poly1305};
+use rand::{rngs::OsRng, RngCore};
+use std::fs::{self, File};
+use std::io::{self, copy, Read, Write};
+use std::sync::Mutex;
+
+// Size of the random salt and nonce stored at the beginning of every encrypted file
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 19;
+// Data is encrypted in small chunk of 500 bytes, each chunk get a 16 bytes authentication tag
+const BUFFER_LEN: usize = 500;
+const TAG_LEN: usize = 16;
+
+// The passphrase is asked once per invocation of rmt and kept for the next elements
+static PASSPHRASE_CACHE: Mutex<Option<String>> = Mutex::new(None);
+
+// Passphrase given by the user, the real key is derived from it with the salt of each file.
+// When the rmt agent is running, it does the derivation and the passphrase is not needed.
+pub enum EncryptionKey {
+    Passphrase(String),
+    Agent { is_test: bool },
+}
+
+impl EncryptionKey {
+    pub fn new(passphrase: String) -> Self {
+        Self::Passphrase(passphrase)
+    }
+
+    // Prompt the user for a passphrase, with a confirmation when it is used to encrypt new data
+    pub fn ask(prompt: &str, with_confirmation: bool) -> Result<Self, io::Error> {
+        let mut password = dialoguer::Password::new();
+        password.with_prompt(prompt);
+        if with_confirmation {
+            password.with_confirmation(format!("Confirm {}", prompt), "Inputs do not match");
+        }
+        Ok(Self::new(password.interact()?))
+    }
+
+    // Use the rmt agent if it is running, otherwise the passphrase already given or a new prompt
+    pub fn get(is_test: bool, with_confirmation: bool) -> Result<Self, io::Error> {
+        if agent_manager::is_agent_running(is_test) {
+            return Ok(Self::Agent { is_test });
+        }
+
+        let mut passphrase_cache = PASSPHRASE_CACHE.lock().unwrap();
+        if let Some(passphrase) = passphrase_cache.as_ref() {
+            return Ok(Self::new(passphrase.clone()));
+        }
+        let encryption_key = Self::ask("Encryption key", with_confirmation)?;
+        if let Self::Passphrase(passphrase) = &encryption_key {
+            *passphrase_cache = Some(passphrase.clone());
+        }
+        Ok(encryption_key)
+    }
+
+    fn derive(&self, salt: &[u8]) -> Result<Vec<u8>, io::Error> {
+        match self {
+            Self::Passphrase(passphrase) => derive_key(passphrase, salt),
+            Self::Agent { is_test } => agent_manager::derive_key(*is_test, salt),
+        }
+    }
+}
+
+// turn insecure passphrase into secure key
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let argon2_config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        hash_length: 32,
+        lanes: 8,
+        mem_cost: 16 * 1024,
+        time_cost: 8,
+        ..Default::default()
+    };
+    argon2::hash_raw(passphrase.as_bytes(), salt, &argon2_config).map_err(io::Error::other)
+}
+
+// Read until the buffer is full or the end of the reader is reached
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<usize, io::Error> {
+    let mut total = 0;
+    while total < buffer.len() {
+        match reader.read(&mut buffer[total..]) {
+            Ok(0) => break,
+            Ok(read_count) => total += read_count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+fn encrypt_stream<R: Read, W: Write>(
+    source: &mut R,
+    dist: &mut W,
+    key: &EncryptionKey,
+) -> Result<(), io::Error> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let derived_key = key.derive(&salt)?;
+    let aead = XChaCha20Poly1305::new(derived_key[..].into());
+    let mut stream_encryptor = stream::EncryptorBE32::from_aead(aead, nonce[..].into());
+
+    // store salt and nonce in the encrypted file to be used when decrypting
+    dist.write_all(&salt)?;
+    dist.write_all(&nonce)?;
+
+    let mut buffer = [0u8; BUFFER_LEN];
+    loop {
+        let read_count = read_full(source, &mut buffer)?;
+
+        if read_count == BUFFER_LEN {
+            let ciphertext = stream_encryptor
+                .encrypt_next(buffer.as_slice())
+                .map_err(|_| io::Error::other("Error encrypting file"))?;
+            dist.write_all(&ciphertext)?;
+        } else {
+            let ciphertext = stream_encryptor
+                .encrypt_last(&buffer[..read_count])
+                .map_err(|_| io::Error::other("Error encrypting file"))?;
+            dist.write_all(&ciphertext)?;
+            break;
+        }
+    }
+    dist.flush()
+}
+
+// Decrypt an encrypted stream on the fly, chunk by chunk
+pub struct DecryptReader<R: Read> {
+    inner: R,
+    stream_decryptor: Option<stream::DecryptorBE32<XChaCha20Poly1305>>,
+    plaintext: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    // The first chunk is decrypted right away, so a wrong key is detected at creation
+    pub fn new(mut inner: R, key: &EncryptionKey) -> Result<Self, io::Error> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        if read_full(&mut inner, &mut salt)? != SALT_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Error reading salt.",
+            ));
+        }
+        if read_full(&mut inner, &mut nonce)? != NONCE_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Error reading nonce.",
+            ));
+        }
+
+        let derived_key = key.derive(&salt)?;
+        let aead = XChaCha20Poly1305::new(derived_key[..32].into());
+        let mut decrypt_reader = Self {
+            inner,
+            stream_decryptor: Some(stream::DecryptorBE32::from_aead(aead, nonce[..].into())),
+            plaintext: Vec::new(),
+            position: 0,
+        };
+        if let Err(e) = decrypt_reader.decrypt_next_chunk() {
+            // the passphrase is probably wrong, it is asked again for the next element
+            if let EncryptionKey::Passphrase(_) = key {
+                *PASSPHRASE_CACHE.lock().unwrap() = None;
+            }
+            return Err(e);
+        }
+        Ok(decrypt_reader)
+    }
+
+    fn decrypt_next_chunk(&mut self) -> Result<(), io::Error> {
+        let mut buffer = [0u8; BUFFER_LEN + TAG_LEN];
+        let read_count = read_full(&mut self.inner, &mut buffer)?;
+
+        let plaintext = if read_count == buffer.len() {
+            self.stream_decryptor
+                .as_mut()
+                .unwrap()
+                .decrypt_next(buffer.as_slice())
+        } else {
+            self.stream_decryptor
+                .take()
+                .unwrap()
+                .decrypt_last(&buffer[..read_count])
+        };
+
+        self.plaintext = plaintext
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Error decrypting file"))?;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.stream_decryptor.is_none() {
+                return Ok(0);
+            }
+            self.decrypt_next_chunk()?;
+        }
+        let count = buf.len().min(self.plaintext.len() - self.position);
+        buf[..count].copy_from_slice(&self.plaintext[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+pub fn encrypt_element(
+    source_path: &str,
+    dist_path: &str,
+    key: &EncryptionKey,
+) -> Result<(), io::Error> {
+    let mut source_file = File::open(source_path)?;
+    let mut dist_file = File::create(dist_path)?;
+
+    encrypt_stream(&mut source_file, &mut dist_file, key).inspect_err(|_| {
+        let _ = fs::remove_file(dist_path);
+    })
+}
+
+pub fn decrypt_element(
+    encrypted_path: &str,
+    dist_path: &str,
+    key: &EncryptionKey,
+) -> Result<(), io::Error> {
+    let mut decrypt_reader = DecryptReader::new(File::open(encrypted_path)?, key)?;
+    let mut dist_file = File::create(dist_path)?;
+
+    copy(&mut decrypt_reader, &mut dist_file)
+        .map(|_| ())
+        .inspect_err(|_| {
+            let _ = fs::remove_file(dist_path);
+        })
+}
+
+// Check if the key is the one used to encrypt the file
+pub fn is_valid_key(encrypted_path: &str, key: &EncryptionKey) -> bool {
+    match File::open(encrypted_path) {
+        Ok(encrypted_file) => DecryptReader::new(encrypted_file, key).is_ok(),
+        Err(_) => false,
+    }
+}
+
+// Encrypt the file again with a new key, the plain content is never written on the disk.
+// The result is written next to the file and then renamed over it, so the file is either
+// fully encrypted with the old key or fully encrypted with the new one.
+pub fn reencrypt_element(
+    encrypted_path: &str,
+    old_key: &EncryptionKey,
+    new_key: &EncryptionKey,
+) -> Result<(), io::Error> {
+    let temporary_path = get_rekey_temporary_path(encrypted_path);
+
+    let mut decrypt_reader = DecryptReader::new(File::open(encrypted_path)?, old_key)?;
+    let mut temporary_file = File::create(&temporary_path)?;
+
+    let result = encrypt_stream(&mut decrypt_reader, &mut temporary_file, new_key)
+        .and_then(|_| temporary_file.sync_all());
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temporary_path);
+        return Err(e);
+    }
+
+    fs::rename(&temporary_path, encrypted_path)
+}
+
+pub fn get_rekey_temporary_path(encrypted_path: &str) -> String {
+    format!("{}.rekey", encrypted_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager;
+    use std::path::MAIN_SEPARATOR;
+
+    fn get_test_file_path(name: &str) -> String {
+        format!(
+            "{}{}{}",
+            structure_manager::get_trash_directory_path(true),
+            MAIN_SEPARATOR,
+            name
+        )
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_element() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+
+        let source_path = get_test_file_path("plain");
+        let encrypted_path = get_test_file_path("encrypted");
+        let decrypted_path = get_test_file_path("decrypted");
+        // more than one chunk, and not a multiple of the chunk size
+        let content: Vec<u8> = (0..1234).map(|i| (i % 251) as u8).collect();
+        fs::write(&source_path, &content).unwrap();
+
+        let key = EncryptionKey::new("rmt".to_string());
+        encrypt_element(&source_path, &encrypted_path, &key).unwrap();
+        assert_ne!(fs::read(&encrypted_path).unwrap(), content);
+
+        decrypt_element(&encrypted_path, &decrypted_path, &key).unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), content);
+
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_is_forgotten() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+
+        let source_path = get_test_file_path("forgotten_plain");
+        let encrypted_path = get_test_file_path("forgotten_encrypted");
+        fs::write(&source_path, "rmt").unwrap();
+        encrypt_element(
+            &source_path,
+            &encrypted_path,
+            &EncryptionKey::new("rmt".to_string()),
+        )
+        .unwrap();
+
+        *PASSPHRASE_CACHE.lock().unwrap() = Some("wrong".to_string());
+        assert!(!is_valid_key(
+            &encrypted_path,
+            &EncryptionKey::new("wrong".to_string())
+        ));
+        assert!(PASSPHRASE_CACHE.lock().unwrap().is_none());
+
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_reencrypt_element() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+
+        let source_path = get_test_file_path("plain");
+        let encrypted_path = get_test_file_path("encrypted");
+        let decrypted_path = get_test_file_path("decrypted");
+        let content = vec![42u8; BUFFER_LEN * 2];
+        fs::write(&source_path, &content).unwrap();
+
+        let old_key = EncryptionKey::new("old".to_string());
+        let new_key = EncryptionKey::new("new".to_string());
+        encrypt_element(&source_path, &encrypted_path, &old_key).unwrap();
+        assert!(is_valid_key(&encrypted_path, &old_key));
+
+        reencrypt_element(&encrypted_path, &old_key, &new_key).unwrap();
+        assert!(!is_valid_key(&encrypted_path, &old_key));
+        assert!(is_valid_key(&encrypted_path, &new_key));
+        assert!(!std::path::Path::new(&get_rekey_temporary_path(&encrypted_path)).exists());
+
+        decrypt_element(&encrypted_path, &decrypted_path, &new_key).unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), content);
+
+        structure_manager::clear_structure(is_test);
+    }
+}
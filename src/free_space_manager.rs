@@ -0,0 +1,276 @@
+// rmt can keep a part of the filesystem of the trash free (min_free_space in the configuration).
+// The free space is checked before and after every deletion, and the oldest elements which are
+// not pinned (rmt --pin) are purged until they free the missing space. Nothing is purged when
+// the space is taken outside of the trash and purging all the trash wouldn't be enough.
+
+use crate::config::Config;
+use crate::data_manager;
+use crate::display_manager::{format_size, SizeUnits};
+use crate::lock_manager;
+use crate::structure_manager::get_trash_directory_path;
+use crate::trash_item::TrashItem;
+use crate::trash_manager;
+
+use colored::Colorize;
+use rusqlite::Connection;
+use std::io;
+
+// Space of the filesystem containing a path, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub available: u64, // free for a user without privileges
+    pub total: u64,
+}
+
+impl DiskSpace {
+    pub fn get_free_percentage(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+        self.available as f64 * 100.0 / self.total as f64
+    }
+
+    // Bytes to free to reach the percentage of free space, 0 if it is already reached
+    pub fn get_missing_space(&self, min_free_space: f64) -> u64 {
+        let min_available = (self.total as f64 * min_free_space / 100.0).ceil() as u64;
+        min_available.saturating_sub(self.available)
+    }
+}
+
+#[cfg(unix)]
+pub fn get_disk_space(path: &str) -> io::Result<DiskSpace> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    let c_path = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: the path is a valid C string and stat is written by statvfs when it succeeds
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)] // the types of the fields depend on the platform
+    Ok(DiskSpace {
+        available: stat.f_bavail as u64 * stat.f_frsize as u64,
+        total: stat.f_blocks as u64 * stat.f_frsize as u64,
+    })
+}
+
+// The free space is only known on unix systems, elsewhere nothing is ever purged
+#[cfg(not(unix))]
+pub fn get_disk_space(_path: &str) -> io::Result<DiskSpace> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "the free space can't be known on this system",
+    ))
+}
+
+// Percentage of the filesystem containing the path which is free for a user without privileges
+pub fn get_free_space_percentage(path: &str) -> io::Result<f64> {
+    get_disk_space(path).map(|disk_space| disk_space.get_free_percentage())
+}
+
+// Purge the oldest unpinned elements until the minimum of free space is reached, if one is set.
+// The element which has just been trashed (its hash) is never purged.
+pub fn ensure_free_space(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    trashed_hash: Option<&str>,
+) {
+    let min_free_space = match config.trash.as_ref().and_then(|trash| trash.min_free_space) {
+        Some(min_free_space) => min_free_space as f64,
+        None => return,
+    };
+    // nothing is purged when the free space can't be known
+    let missing_space = match get_disk_space(&get_trash_directory_path(is_test)) {
+        Ok(disk_space) => disk_space.get_missing_space(min_free_space),
+        Err(_) => return,
+    };
+    if missing_space == 0 {
+        return;
+    }
+    free_missing_space(
+        connection,
+        is_test,
+        min_free_space,
+        missing_space,
+        trashed_hash,
+    );
+}
+
+// Purge the oldest unpinned elements but the one just trashed if they can free the missing space,
+// return the number of purged elements
+fn free_missing_space(
+    connection: &Connection,
+    is_test: bool,
+    min_free_space: f64,
+    missing_space: u64,
+    trashed_hash: Option<&str>,
+) -> usize {
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    let trash_items: Vec<TrashItem> =
+        data_manager::find_oldest_unpinned_trash_items(connection, is_test)
+            .expect("Failed to get the elements of the trash")
+            .into_iter()
+            .filter(|trash_item| Some(trash_item.hash.as_str()) != trashed_hash)
+            .collect();
+    let purgeable_space: u64 = trash_items.iter().map(TrashItem::get_stored_size).sum();
+    if purgeable_space < missing_space {
+        println!(
+            "{}: less than {} of the disk is free, but the unpinned elements of the trash only take {} of the {} missing, nothing is purged. Free some space outside of the trash.",
+            "Warning".yellow().bold(),
+            format!("{}%", min_free_space).green().bold(),
+            format_size(purgeable_space, SizeUnits::Iec),
+            format_size(missing_space, SizeUnits::Iec).bold()
+        );
+        return 0;
+    }
+
+    println!(
+        "Less than {} of the disk is free, the oldest elements of the trash are purged:",
+        format!("{}%", min_free_space).green().bold()
+    );
+    evict_oldest_elements(connection, is_test, &trash_items, missing_space)
+}
+
+// Purge the elements one by one, the oldest first, until they free the missing space, return
+// the number of purged elements
+fn evict_oldest_elements(
+    connection: &Connection,
+    is_test: bool,
+    trash_items: &[TrashItem],
+    missing_space: u64,
+) -> usize {
+    let mut freed_space = 0;
+    let mut evicted_count = 0;
+    for trash_item in trash_items {
+        if freed_space >= missing_space {
+            break;
+        }
+        trash_manager::remove_element(trash_item, is_test);
+        data_manager::delete_trash_item_by_id(connection, is_test, trash_item.id);
+        freed_space += trash_item.get_stored_size();
+        evicted_count += 1;
+    }
+    evicted_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager::{self, get_path_in_trash};
+    use crate::trash_item::TrashItem;
+    use std::fs;
+
+    #[test]
+    fn test_get_free_space_percentage() {
+        let free_space = get_free_space_percentage("/").unwrap();
+        assert!((0.0..=100.0).contains(&free_space));
+        assert!(get_free_space_percentage("/nonexistent_rmt_directory").is_err());
+    }
+
+    #[test]
+    fn test_get_missing_space() {
+        let disk_space = DiskSpace {
+            available: 150,
+            total: 1000,
+        };
+        assert_eq!(disk_space.get_free_percentage(), 15.0);
+        assert_eq!(disk_space.get_missing_space(10.0), 0);
+        assert_eq!(disk_space.get_missing_space(20.0), 50);
+    }
+
+    #[test]
+    fn test_keep_trashed_element() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        // the element just trashed alone takes more than the missing space
+        let mut hashes = Vec::new();
+        for (date, (name, size)) in [("old", 6), ("trashed", 100)].iter().enumerate() {
+            let hash = sha256::digest(format!("keep_{}", name));
+            fs::write(get_path_in_trash(&hash, is_test), "a".repeat(*size)).unwrap();
+            let trash_item = TrashItem::new(
+                name.to_string(),
+                hash.clone(),
+                "/home/user".to_string(),
+                date as i64,
+                *size as u64,
+                None,
+                false,
+                false,
+                false,
+            );
+            data_manager::insert_trash_item(&connection, &trash_item, is_test);
+            hashes.push(hash);
+        }
+
+        let purged_count = free_missing_space(&connection, is_test, 10.0, 50, Some(&hashes[1]));
+        assert_eq!(purged_count, 0);
+        assert_eq!(
+            data_manager::find_all_trash_items(&connection, is_test).len(),
+            2
+        );
+        assert!(std::path::Path::new(&get_path_in_trash(&hashes[1], is_test)).exists());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_evict_oldest_elements() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        for (date, name) in ["pinned", "oldest", "newest"].iter().enumerate() {
+            let hash = sha256::digest(format!("evict_{}", name));
+            fs::write(get_path_in_trash(&hash, is_test), name).unwrap();
+            let trash_item = TrashItem::new(
+                name.to_string(),
+                hash,
+                "/home/user".to_string(),
+                date as i64,
+                6,
+                None,
+                false,
+                false,
+                false,
+            );
+            data_manager::insert_trash_item(&connection, &trash_item, is_test);
+        }
+        let pinned_id = data_manager::find_all_trash_items(&connection, is_test)
+            .into_iter()
+            .find(|trash_item| trash_item.name == "pinned")
+            .unwrap()
+            .id;
+        assert!(
+            data_manager::set_trash_item_pinned(&connection, is_test, pinned_id, true).unwrap()
+        );
+
+        // the oldest unpinned element frees enough space
+        let trash_items =
+            data_manager::find_oldest_unpinned_trash_items(&connection, is_test).unwrap();
+        assert_eq!(trash_items.len(), 2);
+        let evicted_count = evict_oldest_elements(&connection, is_test, &trash_items, 4);
+        assert_eq!(evicted_count, 1);
+
+        let names: Vec<String> = data_manager::find_all_trash_items(&connection, is_test)
+            .into_iter()
+            .map(|trash_item| trash_item.name)
+            .collect();
+        assert_eq!(names, vec!["newest", "pinned"]);
+        assert!(!std::path::Path::new(&get_path_in_trash(
+            &sha256::digest("evict_oldest"),
+            is_test
+        ))
+        .exists());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::checksum_manager;
+use crate::journal_manager;
+use crate::structure_manager::{
+    get_home_directory_path, get_path_in_trash, get_trash_directory_path,
+};
+use crate::{data_manager, display_manager, lock_manager, trash_item::TrashItem};
+
+use colored::Colorize;
+use fs_extra::dir::get_size;
+use rusqlite::Connection;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+// Temporary files created inside the trash while adding, restoring, rekeying or converting an
+// element. The converted element isn't one of them, an interrupted conversion is finished with it.
+const DECRYPTED_TEMPORARY_NAME: &str = "decrypted.zip";
+const TEMPORARY_EXTENSIONS: [&str; 4] = [".rekey", ".zip", ".decrypted.zip", ".convert"];
+
+// Everything that doesn't match between the database and the trash directory
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Inconsistencies {
+    pub missing_elements: Vec<TrashItem>, // in the database but not in the trash directory
+    pub orphan_elements: Vec<String>, // hash of the elements in the trash directory but not in the database
+    pub temporary_files: Vec<String>, // names of the files left by an interrupted operation
+    pub unreadable_journal_entries: Vec<(i64, String)>, // id and description of the operation
+}
+
+impl Inconsistencies {
+    pub fn is_empty(&self) -> bool {
+        self.missing_elements.is_empty()
+            && self.orphan_elements.is_empty()
+            && self.temporary_files.is_empty()
+            && self.unreadable_journal_entries.is_empty()
+    }
+}
+
+// Elements are stored under their sha256 hash
+fn is_hash_name(name: &str) -> bool {
+    name.len() == 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+fn is_temporary_name(name: &str) -> bool {
+    name == DECRYPTED_TEMPORARY_NAME
+        || TEMPORARY_EXTENSIONS
+            .iter()
+            .any(|extension| name.strip_suffix(extension).is_some_and(is_hash_name))
+}
+
+pub fn find_inconsistencies(connection: &Connection, is_test: bool) -> Inconsistencies {
+    let trash_items = data_manager::find_all_trash_items(connection, is_test);
+    let mut inconsistencies = Inconsistencies::default();
+
+    let mut names_in_trash: Vec<String> = fs::read_dir(get_trash_directory_path(is_test))
+        .expect("Unable to read the trash directory")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    names_in_trash.sort();
+
+    for name in names_in_trash {
+        if is_temporary_name(&name) {
+            inconsistencies.temporary_files.push(name);
+        } else if is_hash_name(&name) && !trash_items.iter().any(|item| item.hash == name) {
+            inconsistencies.orphan_elements.push(name);
+        }
+    }
+
+    inconsistencies.missing_elements = trash_items
+        .into_iter()
+        .filter(|item| !Path::new(&get_path_in_trash(&item.hash, is_test)).exists())
+        .collect();
+    inconsistencies.unreadable_journal_entries =
+        journal_manager::find_unreadable_entries(connection, is_test);
+
+    inconsistencies
+}
+
+// An element found in the trash without any information in the database, with the checksums of
+// its files
+struct OrphanElement {
+    trash_item: TrashItem,
+    checksums: Vec<(String, String)>,
+}
+
+// Build the trash item of an element found in the trash without any information in the database.
+// The name and the type can be found back from a zip archive, otherwise the hash is used as name.
+// An encrypted element can't be recognized, it is adopted as a file and restored as it is.
+fn build_orphan_element(hash: &str, is_test: bool) -> OrphanElement {
+    let path_in_trash = get_path_in_trash(hash, is_test);
+    let size = get_size(&path_in_trash).unwrap_or(0);
+    let mut name = format!("recovered_{}", &hash[..8]);
+    let mut is_folder = Path::new(&path_in_trash).is_dir();
+    let mut is_compressed = false;
+
+    let mut signature = [0u8; 4];
+    let is_zip = !is_folder
+        && File::open(&path_in_trash)
+            .and_then(|mut f| f.read_exact(&mut signature))
+            .is_ok()
+        && signature == *b"PK\x03\x04";
+    if is_zip {
+        if let Ok(mut zip_arc) = File::open(&path_in_trash).map(ZipArchive::new) {
+            if let Some(Ok(f)) = zip_arc.as_mut().ok().map(|zip_arc| zip_arc.by_index(0)) {
+                is_compressed = true;
+                is_folder = f.is_dir();
+                name = f.name().trim_end_matches('/').to_string();
+            }
+        }
+    }
+
+    let mut trash_item = TrashItem::new(
+        name,
+        hash.to_string(),
+        get_home_directory_path(),
+        chrono::offset::Utc::now().timestamp(),
+        size,
+        if is_compressed { Some(size) } else { None },
+        is_folder,
+        false,
+        is_compressed,
+    );
+    let checksums = checksum_manager::compute_stored_checksums(&trash_item, is_test).ok();
+    trash_item.content_hash = checksums
+        .as_ref()
+        .map(|checksums| checksums.content_hash.clone());
+    OrphanElement {
+        trash_item,
+        checksums: checksums
+            .map(|checksums| checksums.files)
+            .unwrap_or_default(),
+    }
+}
+
+// Save the element in the database with its checksums, like a trashed element
+fn adopt_orphan_element(connection: &Connection, orphan_element: &OrphanElement, is_test: bool) {
+    data_manager::insert_trash_item(connection, &orphan_element.trash_item, is_test);
+    data_manager::insert_checksums(
+        connection,
+        &orphan_element.trash_item.hash,
+        &orphan_element.checksums,
+        is_test,
+    );
+}
+
+fn remove_from_trash(name: &str, is_test: bool) {
+    let path_in_trash = get_path_in_trash(name, is_test);
+    if Path::new(&path_in_trash).is_dir() {
+        fs::remove_dir_all(&path_in_trash).unwrap();
+    } else {
+        fs::remove_file(&path_in_trash).unwrap();
+    }
+}
+
+// Report the differences between the database and the trash directory and offer to fix them.
+// Return true if the trash is consistent at the end.
+pub fn check_trash(connection: &Connection, is_test: bool) -> bool {
+    // the temporary files of an operation in progress in another rmt process are not left behind
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    let inconsistencies = find_inconsistencies(connection, is_test);
+    if inconsistencies.is_empty() {
+        println!("{} the trash is consistent.", "✔".green().bold());
+        return true;
+    }
+    let mut is_consistent = true;
+
+    if !inconsistencies.missing_elements.is_empty() {
+        println!(
+            "{} elements are saved in the database but missing from the trash:",
+            inconsistencies
+                .missing_elements
+                .len()
+                .to_string()
+                .red()
+                .bold()
+        );
+        inconsistencies
+            .missing_elements
+            .iter()
+            .for_each(|trash_item| println!("   {} (id {})", trash_item, trash_item.id));
+        if display_manager::get_user_choice(
+            "What do you want to do with them ?",
+            &["Forget them", "Leave them"],
+        ) == 0
+        {
+            inconsistencies
+                .missing_elements
+                .iter()
+                .for_each(|trash_item| {
+                    data_manager::delete_trash_item(connection, trash_item.id, is_test)
+                });
+        } else {
+            is_consistent = false;
+        }
+    }
+
+    if !inconsistencies.orphan_elements.is_empty() {
+        println!(
+            "{} elements are in the trash but missing from the database:",
+            inconsistencies
+                .orphan_elements
+                .len()
+                .to_string()
+                .red()
+                .bold()
+        );
+        let orphan_elements: Vec<OrphanElement> = inconsistencies
+            .orphan_elements
+            .iter()
+            .map(|hash| build_orphan_element(hash, is_test))
+            .collect();
+        orphan_elements.iter().for_each(|orphan_element| {
+            println!(
+                "   {} ({})",
+                orphan_element.trash_item, orphan_element.trash_item.hash
+            )
+        });
+        match display_manager::get_user_choice(
+            "What do you want to do with them ?",
+            &["Adopt them", "Delete them", "Leave them"],
+        ) {
+            0 => orphan_elements.iter().for_each(|orphan_element| {
+                adopt_orphan_element(connection, orphan_element, is_test)
+            }),
+            1 => orphan_elements.iter().for_each(|orphan_element| {
+                remove_from_trash(&orphan_element.trash_item.hash, is_test)
+            }),
+            _ => is_consistent = false,
+        }
+    }
+
+    if !inconsistencies.temporary_files.is_empty() {
+        println!(
+            "{} temporary files have been left by an interrupted operation:",
+            inconsistencies
+                .temporary_files
+                .len()
+                .to_string()
+                .red()
+                .bold()
+        );
+        inconsistencies
+            .temporary_files
+            .iter()
+            .for_each(|name| println!("   {}", name));
+        if display_manager::get_user_choice(
+            "What do you want to do with them ?",
+            &["Delete them", "Leave them"],
+        ) == 0
+        {
+            inconsistencies
+                .temporary_files
+                .iter()
+                .for_each(|name| remove_from_trash(name, is_test));
+        } else {
+            is_consistent = false;
+        }
+    }
+
+    if !inconsistencies.unreadable_journal_entries.is_empty() {
+        println!(
+            "{} interrupted operations can't be recovered, their files may need to be checked by hand:",
+            inconsistencies
+                .unreadable_journal_entries
+                .len()
+                .to_string()
+                .red()
+                .bold()
+        );
+        inconsistencies
+            .unreadable_journal_entries
+            .iter()
+            .for_each(|(journal_id, description)| {
+                println!("   {} (id {})", description, journal_id)
+            });
+        if display_manager::get_user_choice(
+            "What do you want to do with them ?",
+            &["Forget them", "Leave them"],
+        ) == 0
+        {
+            inconsistencies
+                .unreadable_journal_entries
+                .iter()
+                .for_each(|(journal_id, _)| {
+                    data_manager::delete_journal_entry(connection, *journal_id, is_test)
+                });
+        } else {
+            is_consistent = false;
+        }
+    }
+    is_consistent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager;
+    use std::path::MAIN_SEPARATOR;
+
+    #[test]
+    fn test_find_inconsistencies() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let missing_hash = sha256::digest("missing");
+        let orphan_hash = sha256::digest("orphan");
+        let trash_item = TrashItem::new(
+            "missing.txt".to_string(),
+            missing_hash,
+            "home/user".to_string(),
+            0,
+            10,
+            None,
+            false,
+            false,
+            false,
+        );
+        data_manager::insert_trash_item(&connection, &trash_item, is_test);
+        fs::write(get_path_in_trash(&orphan_hash, is_test), "orphan").unwrap();
+        fs::write(get_path_in_trash(DECRYPTED_TEMPORARY_NAME, is_test), "").unwrap();
+
+        let inconsistencies = find_inconsistencies(&connection, is_test);
+        assert_eq!(inconsistencies.missing_elements.len(), 1);
+        assert_eq!(inconsistencies.missing_elements[0].name, "missing.txt");
+        assert_eq!(inconsistencies.orphan_elements, vec![orphan_hash.clone()]);
+        assert_eq!(
+            inconsistencies.temporary_files,
+            vec![DECRYPTED_TEMPORARY_NAME.to_string()]
+        );
+
+        let orphan_trash_item = build_orphan_element(&orphan_hash, is_test).trash_item;
+        assert!(!orphan_trash_item.is_folder);
+        assert!(!orphan_trash_item.is_compressed);
+        assert_eq!(orphan_trash_item.real_size, 6);
+        assert_eq!(
+            orphan_trash_item.content_hash,
+            Some(sha256::digest("orphan"))
+        );
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_adopt_orphan_directory() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let orphan_hash = sha256::digest("orphan_directory");
+        let path_in_trash = get_path_in_trash(&orphan_hash, is_test);
+        fs::create_dir_all(format!("{}{}sub", path_in_trash, MAIN_SEPARATOR)).unwrap();
+        fs::write(
+            format!("{0}{1}sub{1}a.txt", path_in_trash, MAIN_SEPARATOR),
+            "a",
+        )
+        .unwrap();
+        fs::write(format!("{}{}b.txt", path_in_trash, MAIN_SEPARATOR), "b").unwrap();
+
+        let orphan_element = build_orphan_element(&orphan_hash, is_test);
+        assert!(orphan_element.trash_item.is_folder);
+        adopt_orphan_element(&connection, &orphan_element, is_test);
+        let checksums = data_manager::find_checksums(&connection, &orphan_hash, is_test).unwrap();
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(checksums, orphan_element.checksums);
+        assert!(find_inconsistencies(&connection, is_test).is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(is_hash_name(&sha256::digest("rmt")));
+        assert!(!is_hash_name("test_trash.db"));
+        assert!(!is_hash_name(&sha256::digest("rmt").to_uppercase()));
+        assert!(is_temporary_name("decrypted.zip"));
+        assert!(is_temporary_name(&format!(
+            "{}.rekey",
+            sha256::digest("rmt")
+        )));
+        assert!(is_temporary_name(&format!(
+            "{}.decrypted.zip",
+            sha256::digest("rmt")
+        )));
+        assert!(!is_temporary_name(&format!(
+            "{}.converted",
+            sha256::digest("rmt")
+        )));
+        assert!(!is_temporary_name("config.rekey"));
+    }
+}
@@ -0,0 +1,268 @@
+// Search the content of the trashed files (rmt --grep), the files inside the trashed directories
+// included. The stored elements are decompressed on the fly, and the encrypted ones are only
+// searched with --decrypt, once the encryption key is given (or when the agent has it).
+
+use crate::data_manager;
+use crate::encryption_manager::EncryptionKey;
+use crate::inspect_manager::StoredElement;
+use crate::lock_manager;
+use crate::trash_item::TrashItem;
+
+use colored::Colorize;
+use regex::bytes::Regex;
+use rusqlite::Connection;
+use std::io::{self, BufRead, BufReader, Read};
+
+// A line of a trashed file matching the pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub inner_path: String,         // empty when the element is the file
+    pub line_number: Option<usize>, // None for a binary file, only the first match is reported
+    pub line: Vec<u8>,
+}
+
+// The matching lines of a file, a binary file (containing a NUL byte) gives a single match
+fn search_file(inner_path: &str, reader: impl Read, pattern: &Regex) -> io::Result<Vec<GrepMatch>> {
+    let mut reader = BufReader::new(reader);
+    let mut matches = Vec::new();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    let mut is_binary = false;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(matches);
+        }
+        line_number += 1;
+        if !is_binary && line.contains(&0) {
+            is_binary = true;
+        }
+        // the text lines matched before are replaced by the binary match
+        if is_binary && (pattern.is_match(&line) || !matches.is_empty()) {
+            return Ok(vec![GrepMatch {
+                inner_path: inner_path.to_string(),
+                line_number: None,
+                line: Vec::new(),
+            }]);
+        }
+        if is_binary || !pattern.is_match(&line) {
+            continue;
+        }
+        while line
+            .last()
+            .is_some_and(|byte| *byte == b'\n' || *byte == b'\r')
+        {
+            line.pop();
+        }
+        matches.push(GrepMatch {
+            inner_path: inner_path.to_string(),
+            line_number: Some(line_number),
+            line: line.clone(),
+        });
+    }
+}
+
+// The matching lines of every file of the stored element
+pub fn search_element(
+    stored_element: &mut StoredElement,
+    pattern: &Regex,
+) -> io::Result<Vec<GrepMatch>> {
+    let mut entries = stored_element.entries()?;
+    entries.sort_by(|entry, other| entry.inner_path.cmp(&other.inner_path));
+    let mut matches = Vec::new();
+    for entry in entries {
+        if entry.is_dir {
+            continue;
+        }
+        let reader = stored_element.open_file(&entry.inner_path)?;
+        matches.append(&mut search_file(&entry.inner_path, reader, pattern)?);
+    }
+    Ok(matches)
+}
+
+// "4/src/main.rs:12:fn main() {" with the matching parts highlighted, like grep
+fn display_match(trash_item: &TrashItem, grep_match: &GrepMatch, pattern: &Regex) -> String {
+    let location = if grep_match.inner_path.is_empty() {
+        trash_item.id.to_string()
+    } else {
+        format!("{}/{}", trash_item.id, grep_match.inner_path)
+    };
+    let line_number = match grep_match.line_number {
+        Some(line_number) => line_number,
+        None => return format!("{}: binary file matches", location.magenta()),
+    };
+    let mut line = String::new();
+    let mut end = 0;
+    for found in pattern.find_iter(&grep_match.line) {
+        line.push_str(&String::from_utf8_lossy(
+            &grep_match.line[end..found.start()],
+        ));
+        line.push_str(
+            &String::from_utf8_lossy(found.as_bytes())
+                .red()
+                .bold()
+                .to_string(),
+        );
+        end = found.end();
+    }
+    line.push_str(&String::from_utf8_lossy(&grep_match.line[end..]));
+    format!(
+        "{}:{}:{}",
+        location.magenta(),
+        line_number.to_string().green(),
+        line
+    )
+}
+
+// Show the lines of the trashed files matching the pattern. Like grep, return false when nothing
+// matches or an element can't be read.
+pub fn grep_elements(
+    connection: &Connection,
+    is_test: bool,
+    pattern: &Regex,
+    is_decrypting: bool,
+) -> bool {
+    let mut trash_items = data_manager::find_all_trash_items(connection, is_test);
+    trash_items.sort_by_key(|trash_item| trash_item.id);
+
+    let encrypted_count = trash_items
+        .iter()
+        .filter(|trash_item| trash_item.is_encrypted)
+        .count();
+    let encryption_key = if is_decrypting && encrypted_count > 0 {
+        match EncryptionKey::get(is_test, false) {
+            Ok(encryption_key) => Some(encryption_key),
+            Err(e) => {
+                println!("{}: {}.", "Error".red().bold(), e);
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut match_count = 0;
+    let mut is_success = true;
+    for trash_item in &trash_items {
+        if trash_item.is_encrypted && encryption_key.is_none() {
+            continue;
+        }
+        let result = {
+            let _trash_lock = lock_manager::lock_trash(is_test);
+            StoredElement::open(trash_item, encryption_key.as_ref(), is_test)
+                .and_then(|mut stored_element| search_element(&mut stored_element, pattern))
+        };
+        match result {
+            Ok(matches) => {
+                match_count += matches.len();
+                matches.iter().for_each(|grep_match| {
+                    println!("{}", display_match(trash_item, grep_match, pattern))
+                });
+            }
+            Err(e) => {
+                println!(
+                    "{}: unable to search {} ({}).",
+                    "Error".red().bold(),
+                    trash_item.name.red().bold(),
+                    e
+                );
+                is_success = false;
+            }
+        }
+    }
+
+    if encrypted_count > 0 && encryption_key.is_none() {
+        println!(
+            "{} encrypted element(s) not searched, add {} to search them too.",
+            encrypted_count,
+            "--decrypt".bold()
+        );
+    }
+    if match_count == 0 {
+        println!("Nothing matches {} in the trash.", pattern.as_str().bold());
+    }
+    is_success && match_count > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arguments_manager::ArgumentsManager;
+    use crate::structure_manager::{self, get_trash_directory_path};
+    use crate::trash_manager;
+    use std::fs;
+    use std::path::MAIN_SEPARATOR;
+
+    #[test]
+    fn test_search_file() {
+        let pattern = Regex::new("rm[a-z]").unwrap();
+        let matches =
+            search_file("notes.txt", "rmt\nrm -rf\r\nuse rmt\n".as_bytes(), &pattern).unwrap();
+        assert_eq!(
+            matches
+                .iter()
+                .map(|grep_match| (grep_match.line_number, grep_match.line.as_slice()))
+                .collect::<Vec<_>>(),
+            vec![(Some(1), "rmt".as_bytes()), (Some(3), "use rmt".as_bytes())]
+        );
+
+        let matches = search_file("rmt.bin", "rmt\n\0rmt\n".as_bytes(), &pattern).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, None);
+        assert!(search_file("empty.txt", "".as_bytes(), &pattern)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_element() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let directory = format!(
+            "{}{}searched_directory",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        let pattern = Regex::new("fn \\w+").unwrap();
+
+        for compression in [false, true] {
+            config.compression = compression;
+            fs::create_dir_all(format!("{}/src", directory)).unwrap();
+            fs::write(
+                format!("{}/src/main.rs", directory),
+                "// rmt\nfn main() {}\n",
+            )
+            .unwrap();
+            fs::write(format!("{}/README.md", directory), "# rmt").unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &directory,
+                &arguments_manager,
+            );
+            let mut trash_items = data_manager::find_all_trash_items(&connection, is_test);
+            trash_items.sort_by_key(|trash_item| trash_item.id);
+            let trash_item = trash_items.pop().unwrap();
+            let mut stored_element = StoredElement::open(&trash_item, None, is_test).unwrap();
+
+            let matches = search_element(&mut stored_element, &pattern).unwrap();
+            assert_eq!(
+                matches,
+                vec![GrepMatch {
+                    inner_path: "src/main.rs".to_string(),
+                    line_number: Some(2),
+                    line: "fn main() {}".as_bytes().to_vec(),
+                }]
+            );
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
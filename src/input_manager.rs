@@ -8,12 +8,19 @@ use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
 use crossterm::{cursor, execute};
 
 use crate::{
-    data_manager,
+    config::Config,
+    data_manager::{self, TrashItemsOrder},
     display_manager::{self, DisplayInfos},
+    restore_manager::RestoreOptions,
     trash_manager,
 };
 
-pub fn start_display(connection: &Connection, is_test: bool) {
+pub fn start_display(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    restore_options: &RestoreOptions,
+) {
     enable_raw_mode().unwrap();
     let mut stdout = stdout();
     execute!(
@@ -27,6 +34,7 @@ pub fn start_display(connection: &Connection, is_test: bool) {
     let mut display_informations = display_manager::DisplayInfos::new(
         data_manager::get_element_count(connection, is_test)
             .expect("Failed to count all the trash"),
+        config.date_format,
     );
 
     let mut current_selected_item =
@@ -43,17 +51,7 @@ pub fn start_display(connection: &Connection, is_test: bool) {
                     kind: KeyEventKind::Press,
                     state: _,
                 })) => {
-                    if code == KeyCode::Char('d') && modifiers == KeyModifiers::CONTROL {
-                        display_informations.filter.content.clear();
-                    } else if code == KeyCode::Backspace {
-                        display_informations.filter.content.pop();
-                    } else if code == KeyCode::Esc {
-                        display_informations.filter.is_filter = false;
-                    } else if let KeyCode::Char(c) = code {
-                        if (c as u8) >= 33 && (c as u8) <= 126 {
-                            display_informations.filter.content.push(c);
-                        }
-                    }
+                    edit_filter(&mut display_informations, code, modifiers);
                     execute!(stdout, Clear(ClearType::All)).unwrap();
                 }
                 Err(e) => {
@@ -92,6 +90,13 @@ pub fn start_display(connection: &Connection, is_test: bool) {
                     }
                     if code == KeyCode::Esc || code == KeyCode::Char('/') {
                         display_informations.filter.is_filter = true;
+                        display_informations.order = TrashItemsOrder::Relevance;
+                        display_informations.current_cursor_index = 0;
+                        display_informations.current_page = 1;
+                        execute!(stdout, Clear(ClearType::All)).unwrap();
+                    }
+                    if code == KeyCode::Char('s') {
+                        display_informations.order = display_informations.order.next();
                         display_informations.current_cursor_index = 0;
                         display_informations.current_page = 1;
                         execute!(stdout, Clear(ClearType::All)).unwrap();
@@ -121,8 +126,10 @@ pub fn start_display(connection: &Connection, is_test: bool) {
                         );
                         trash_manager::restore_all_elements_selected(
                             connection,
+                            config,
                             is_test,
                             &display_informations.selected_trash_items.restore,
+                            restore_options,
                         );
                         break;
                     }
@@ -146,6 +153,24 @@ pub fn start_display(connection: &Connection, is_test: bool) {
     disable_raw_mode().expect("Unable to disable raw mode");
 }
 
+// Type the query of the filter bar, the spaces separate its words and conditions
+fn edit_filter(display_infos: &mut DisplayInfos, code: KeyCode, modifiers: KeyModifiers) {
+    if code == KeyCode::Char('d') && modifiers == KeyModifiers::CONTROL {
+        display_infos.filter.content.clear();
+    } else if code == KeyCode::Backspace {
+        display_infos.filter.content.pop();
+    } else if code == KeyCode::Esc {
+        display_infos.filter.is_filter = false;
+        if display_infos.order == TrashItemsOrder::Relevance {
+            display_infos.order = TrashItemsOrder::default();
+        }
+    } else if let KeyCode::Char(c) = code {
+        if !c.is_control() {
+            display_infos.filter.content.push(c);
+        }
+    }
+}
+
 fn set_cursor(display_infos: &mut DisplayInfos, top: bool) {
     if display_infos.total_elements == 0 {
         return;
@@ -195,3 +220,50 @@ fn toggle_item(selected_elements: i32, storage: &mut Vec<i32>, linked_storage: &
         storage.push(selected_elements);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DateFormat;
+    use crate::query_manager::Query;
+
+    fn type_filter(display_infos: &mut DisplayInfos, text: &str) {
+        text.chars()
+            .for_each(|c| edit_filter(display_infos, KeyCode::Char(c), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn test_edit_filter_words() {
+        let mut display_infos = DisplayInfos::new(0, DateFormat::default());
+        display_infos.filter.is_filter = true;
+
+        type_filter(&mut display_infos, "rep");
+        edit_filter(&mut display_infos, KeyCode::Char(' '), KeyModifiers::NONE);
+        type_filter(&mut display_infos, "2024");
+        assert_eq!(display_infos.filter.content, "rep 2024");
+        let query = Query::parse(display_infos.filter.get_content()).unwrap();
+        assert_eq!(query.words, vec!["rep".to_string(), "2024".to_string()]);
+        assert_eq!(query.get_search(), "rep 2024");
+    }
+
+    #[test]
+    fn test_edit_filter_conditions() {
+        let mut display_infos = DisplayInfos::new(0, DateFormat::default());
+        display_infos.filter.is_filter = true;
+
+        type_filter(&mut display_infos, "name:foo size>1M");
+        assert_eq!(display_infos.filter.content, "name:foo size>1M");
+        let query = Query::parse(display_infos.filter.get_content()).unwrap();
+        assert_eq!(query.conditions.len(), 2);
+        edit_filter(&mut display_infos, KeyCode::Backspace, KeyModifiers::NONE);
+        type_filter(&mut display_infos, "0K é");
+        assert_eq!(display_infos.filter.content, "name:foo size>10K é");
+
+        edit_filter(
+            &mut display_infos,
+            KeyCode::Char('d'),
+            KeyModifiers::CONTROL,
+        );
+        assert!(display_infos.filter.content.is_empty());
+    }
+}
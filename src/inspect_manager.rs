@@ -0,0 +1,745 @@
+// Read the elements of the trash without restoring them: the stored element is decrypted and
+// decompressed into a copy (rmt --extract), or read on the fly to show a file (rmt --cat) or the
+// entries of a directory (rmt --ls), and the element stays in the trash untouched.
+
+use crate::checksum_manager;
+use crate::data_manager;
+use crate::display_manager::{format_size, SizeUnits};
+use crate::encryption_manager::{decrypt_element, DecryptReader, EncryptionKey};
+use crate::journal_manager::remove_path;
+use crate::lock_manager::{self, TrashLock};
+use crate::restore_manager;
+use crate::structure_manager::get_path_in_trash;
+use crate::trash_item::TrashItem;
+use crate::trash_manager::decompress_element;
+
+use colored::Colorize;
+use fs_extra::dir;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use walkdir::WalkDir;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+// An element of the trash, or a path inside a trashed directory: "4" or "4/src/main.rs"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementPath {
+    pub id: i32,
+    pub inner_path: String, // empty for the element itself
+}
+
+impl ElementPath {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (id, inner_path) = value.split_once('/').unwrap_or((value, ""));
+        match id.parse() {
+            Ok(id) => Ok(Self {
+                id,
+                inner_path: inner_path.trim_matches('/').to_string(),
+            }),
+            Err(_) => Err(format!(
+                "'{}' should start with the id of an element, like 4/src/main.rs",
+                value
+            )),
+        }
+    }
+}
+
+// A file or a directory inside a stored element, its path uses '/' like inside a zip archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub inner_path: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+enum StoredSource {
+    Directory(PathBuf),
+    Archive(ZipArchive<Box<dyn ReadSeek>>),
+    File(Box<dyn Read>),
+}
+
+// A file removed when it is dropped
+struct TemporaryFile(String);
+
+impl Drop for TemporaryFile {
+    fn drop(&mut self) {
+        remove_path(&self.0);
+    }
+}
+
+// A stored element opened for reading, nothing is written on the disk but the decrypted archive
+// of an element both encrypted and compressed
+pub struct StoredElement {
+    name: String,
+    is_folder: bool,
+    real_size: u64,
+    source: StoredSource,
+    _decrypted_file: Option<TemporaryFile>, // removed once the source is closed
+}
+
+impl StoredElement {
+    pub fn open(
+        trash_item: &TrashItem,
+        encryption_key: Option<&EncryptionKey>,
+        is_test: bool,
+    ) -> io::Result<Self> {
+        let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+        let mut decrypted_file = None;
+        let source = match (trash_item.is_encrypted, trash_item.is_compressed) {
+            (true, true) => {
+                // a zip archive needs to be seekable, so it is decrypted in a file next to the
+                // element, under the lock of the trash
+                let decrypted_path = format!("{}.decrypted.zip", path_in_trash);
+                decrypt_element(
+                    &path_in_trash,
+                    &decrypted_path,
+                    encryption_key.ok_or_else(missing_key_error)?,
+                )?;
+                let archive = File::open(&decrypted_path).and_then(|file| {
+                    let reader: Box<dyn ReadSeek> = Box::new(file);
+                    Ok(ZipArchive::new(reader)?)
+                });
+                // an open file can still be read once removed on unix, elsewhere it is removed
+                // with the stored element
+                if fs::remove_file(&decrypted_path).is_err() && archive.is_ok() {
+                    decrypted_file = Some(TemporaryFile(decrypted_path));
+                }
+                StoredSource::Archive(archive?)
+            }
+            (true, false) => StoredSource::File(Box::new(DecryptReader::new(
+                File::open(&path_in_trash)?,
+                encryption_key.ok_or_else(missing_key_error)?,
+            )?)),
+            (false, true) => {
+                let reader: Box<dyn ReadSeek> = Box::new(File::open(&path_in_trash)?);
+                StoredSource::Archive(ZipArchive::new(reader)?)
+            }
+            (false, false) if trash_item.is_folder => {
+                StoredSource::Directory(PathBuf::from(path_in_trash))
+            }
+            (false, false) => StoredSource::File(Box::new(File::open(&path_in_trash)?)),
+        };
+        Ok(Self {
+            name: trash_item.name.clone(),
+            is_folder: trash_item.is_folder,
+            real_size: trash_item.real_size,
+            source,
+            _decrypted_file: decrypted_file,
+        })
+    }
+
+    // Every file and directory of the element, a file element is a single entry with an empty path
+    pub fn entries(&mut self) -> io::Result<Vec<Entry>> {
+        if !self.is_folder {
+            return Ok(vec![Entry {
+                inner_path: String::new(),
+                size: self.real_size,
+                is_dir: false,
+            }]);
+        }
+        let mut entries = Vec::new();
+        match &mut self.source {
+            StoredSource::Directory(directory) => {
+                for entry in WalkDir::new(&directory).min_depth(1) {
+                    let entry = entry?;
+                    entries.push(Entry {
+                        inner_path: entry
+                            .path()
+                            .strip_prefix(&directory)
+                            .unwrap()
+                            .to_string_lossy()
+                            .replace(MAIN_SEPARATOR, "/"),
+                        size: if entry.file_type().is_file() {
+                            entry.metadata()?.len()
+                        } else {
+                            0
+                        },
+                        is_dir: entry.file_type().is_dir(),
+                    });
+                }
+            }
+            // the first entry of the archive is the directory itself
+            StoredSource::Archive(archive) => {
+                for i in 1..archive.len() {
+                    let file = archive.by_index(i)?;
+                    entries.push(Entry {
+                        inner_path: file
+                            .name()
+                            .replace('\\', "/")
+                            .trim_end_matches('/')
+                            .to_string(),
+                        size: file.size(),
+                        is_dir: file.is_dir(),
+                    });
+                }
+            }
+            StoredSource::File(_) => (),
+        }
+        Ok(entries)
+    }
+
+    // Read a file of the element, the empty path is the element itself when it's a file
+    pub fn open_file(&mut self, inner_path: &str) -> io::Result<Box<dyn Read + '_>> {
+        if !self.is_folder && !inner_path.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is a file", self.name),
+            ));
+        }
+        let is_directory_error = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is a directory",
+                    display_inner_path(&self.name, inner_path)
+                ),
+            )
+        };
+        let not_found_error = || {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{} doesn't exist",
+                    display_inner_path(&self.name, inner_path)
+                ),
+            )
+        };
+        if self.is_folder && inner_path.is_empty() {
+            return Err(is_directory_error());
+        }
+        match &mut self.source {
+            StoredSource::Directory(directory) => {
+                let path = directory.join(inner_path);
+                if path.is_dir() {
+                    return Err(is_directory_error());
+                } else if !path.exists() {
+                    return Err(not_found_error());
+                }
+                Ok(Box::new(File::open(path)?))
+            }
+            StoredSource::Archive(archive) => {
+                // the element itself is the first entry of the archive
+                let name = if inner_path.is_empty() {
+                    archive.by_index(0)?.name().to_string()
+                } else {
+                    inner_path.to_string()
+                };
+                let directory_name = format!("{}/", inner_path);
+                if archive.file_names().any(|name| name == directory_name) {
+                    return Err(is_directory_error());
+                }
+                match archive.by_name(&name) {
+                    Ok(file) => Ok(Box::new(file)),
+                    Err(ZipError::FileNotFound) => Err(not_found_error()),
+                    Err(e) => Err(e.into()),
+                }
+            }
+            StoredSource::File(reader) => Ok(Box::new(reader)),
+        }
+    }
+}
+
+// True if the path is the directory or is inside it
+pub fn is_inside(inner_path: &str, directory: &str) -> bool {
+    inner_path == directory
+        || inner_path
+            .strip_prefix(directory)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Match a path inside an element against a glob: * and ? never match a '/', ** matches any number
+// of directories
+pub fn matches_glob(pattern: &str, inner_path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            ['*', '*', rest @ ..] => {
+                // "**/" also matches no directory at all
+                (rest.first() == Some(&'/') && matches(&rest[1..], path))
+                    || (0..=path.len()).any(|i| matches(rest, &path[i..]))
+            }
+            ['*', rest @ ..] => (0..=path.len())
+                .take_while(|&i| i == 0 || path[i - 1] != '/')
+                .any(|i| matches(rest, &path[i..])),
+            ['?', rest @ ..] => !path.is_empty() && path[0] != '/' && matches(rest, &path[1..]),
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let inner_path: Vec<char> = inner_path.chars().collect();
+    matches(&pattern, &inner_path)
+}
+
+// "project/src/main.rs"
+fn display_inner_path(name: &str, inner_path: &str) -> String {
+    if inner_path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", name, inner_path)
+    }
+}
+
+// The entries directly inside the directory, with the total size of their content for the
+// directories, None if the path doesn't exist in the element
+pub fn list_children(entries: &[Entry], inner_path: &str) -> Option<Vec<Entry>> {
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.inner_path == inner_path && !entry.is_dir)
+    {
+        return Some(vec![entry.clone()]);
+    }
+    let prefix = if inner_path.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", inner_path)
+    };
+    let mut children: BTreeMap<&str, Entry> = BTreeMap::new();
+    let mut is_found = inner_path.is_empty();
+    for entry in entries {
+        if entry.inner_path == inner_path {
+            is_found = true;
+        }
+        let rest = match entry.inner_path.strip_prefix(&prefix) {
+            Some(rest) if !rest.is_empty() => rest,
+            _ => continue,
+        };
+        is_found = true;
+        let (name, is_nested) = match rest.split_once('/') {
+            Some((name, _)) => (name, true),
+            None => (rest, false),
+        };
+        let child = children.entry(name).or_insert_with(|| Entry {
+            inner_path: format!("{}{}", prefix, name),
+            size: 0,
+            is_dir: false,
+        });
+        child.is_dir |= is_nested || entry.is_dir;
+        if !entry.is_dir {
+            child.size += entry.size;
+        }
+    }
+    is_found.then(|| children.into_values().collect())
+}
+
+fn missing_key_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "the encryption key is needed")
+}
+
+pub fn get_encryption_key(
+    trash_item: &TrashItem,
+    is_test: bool,
+) -> io::Result<Option<EncryptionKey>> {
+    if trash_item.is_encrypted {
+        EncryptionKey::get(is_test, false).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn find_trash_item(
+    connection: &Connection,
+    is_test: bool,
+    trash_item_id: i32,
+) -> Option<TrashItem> {
+    let trash_item = data_manager::find_trash_item_by_id(connection, is_test, trash_item_id).ok();
+    if trash_item.is_none() {
+        eprintln!(
+            "{}: there is no element with the id {} in the trash.",
+            "Error".red().bold(),
+            trash_item_id.to_string().red().bold()
+        );
+    }
+    trash_item
+}
+
+// Write a copy of the stored element at the extracted path
+pub fn extract_element(
+    trash_item: &TrashItem,
+    extracted_path: &str,
+    encryption_key: Option<&EncryptionKey>,
+    is_test: bool,
+) -> io::Result<()> {
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+    match (trash_item.is_encrypted, trash_item.is_compressed) {
+        (true, true) => {
+            // the archive is decrypted next to the copy, nothing is written in the trash
+            let decrypted_path = format!("{}.decrypted.zip", extracted_path);
+            let result = decrypt_element(
+                &path_in_trash,
+                &decrypted_path,
+                encryption_key.ok_or_else(missing_key_error)?,
+            )
+            .and_then(|_| decompress_element(&decrypted_path, extracted_path));
+            remove_path(&decrypted_path);
+            result
+        }
+        (true, false) => decrypt_element(
+            &path_in_trash,
+            extracted_path,
+            encryption_key.ok_or_else(missing_key_error)?,
+        ),
+        (false, true) => decompress_element(&path_in_trash, extracted_path),
+        (false, false) if Path::new(&path_in_trash).is_dir() => {
+            fs::create_dir_all(extracted_path)?;
+            let mut copy_options = dir::CopyOptions::new();
+            copy_options.content_only = true;
+            dir::copy(&path_in_trash, extracted_path, &copy_options)
+                .map(|_| ())
+                .map_err(|e| io::Error::other(e.to_string()))
+        }
+        (false, false) => fs::copy(&path_in_trash, extracted_path).map(|_| ()),
+    }
+}
+
+// Extract a copy of the elements in the destination directory (the current directory by default),
+// return false if one of them couldn't be extracted
+pub fn extract_elements(
+    connection: &Connection,
+    is_test: bool,
+    trash_items_ids: &[i32],
+    destination: Option<&str>,
+) -> bool {
+    let destination = restore_manager::to_absolute_path(destination.unwrap_or("."));
+    if let Err(e) = fs::create_dir_all(&destination) {
+        println!(
+            "{}: unable to create {} ({}).",
+            "Error".red().bold(),
+            destination.red().bold(),
+            e
+        );
+        return false;
+    }
+
+    let mut is_success = true;
+    for trash_item_id in trash_items_ids {
+        let trash_item = match find_trash_item(connection, is_test, *trash_item_id) {
+            Some(trash_item) => trash_item,
+            None => {
+                is_success = false;
+                continue;
+            }
+        };
+        let encryption_key = match get_encryption_key(&trash_item, is_test) {
+            Ok(encryption_key) => encryption_key,
+            Err(e) => {
+                println!("{}: {}", "Error".red().bold(), e);
+                return false;
+            }
+        };
+
+        let mut extracted_path = format!("{}{}{}", destination, MAIN_SEPARATOR, trash_item.name);
+        if Path::new(&extracted_path).exists() {
+            extracted_path = restore_manager::get_free_path(
+                &destination,
+                &trash_item.name,
+                trash_item.is_folder,
+            );
+        }
+        // the element mustn't be converted or removed by another rmt process during the copy
+        let trash_lock = lock_manager::lock_trash(is_test);
+        let result = extract_element(
+            &trash_item,
+            &extracted_path,
+            encryption_key.as_ref(),
+            is_test,
+        );
+        drop(trash_lock);
+        match result {
+            Ok(()) => {
+                println!(
+                    "{} has been extracted to {}",
+                    trash_item.name.green().bold(),
+                    extracted_path.green().bold()
+                );
+                checksum_manager::verify_restored_element(&trash_item, &extracted_path);
+            }
+            Err(e) => {
+                remove_path(&extracted_path);
+                println!(
+                    "{}: unable to extract {} ({}).",
+                    "Error".red().bold(),
+                    trash_item.name.red().bold(),
+                    e
+                );
+                is_success = false;
+            }
+        }
+    }
+    is_success
+}
+
+// Open the element under the lock of the trash, and give it to the reading function with the
+// lock, which can release it once the files it reads are open
+fn read_element<T>(
+    connection: &Connection,
+    is_test: bool,
+    element_path: &ElementPath,
+    read: impl FnOnce(&mut StoredElement, TrashLock) -> io::Result<T>,
+) -> Option<T> {
+    let trash_item = find_trash_item(connection, is_test, element_path.id)?;
+    let result = get_encryption_key(&trash_item, is_test).and_then(|encryption_key| {
+        let trash_lock = lock_manager::lock_trash(is_test);
+        let mut stored_element =
+            StoredElement::open(&trash_item, encryption_key.as_ref(), is_test)?;
+        read(&mut stored_element, trash_lock)
+    });
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("{}: {}.", "Error".red().bold(), e);
+            None
+        }
+    }
+}
+
+// Write the content of a trashed file on the standard output, return false if it can't be read
+pub fn cat_element(connection: &Connection, is_test: bool, element_path: &ElementPath) -> bool {
+    read_element(
+        connection,
+        is_test,
+        element_path,
+        |stored_element, trash_lock| {
+            let mut reader = stored_element.open_file(&element_path.inner_path)?;
+            // an open file can still be read once the element is converted or removed, so a slow
+            // reader of the output (like a pager) doesn't block the other rmt processes
+            drop(trash_lock);
+            let mut stdout = io::stdout().lock();
+            match io::copy(&mut reader, &mut stdout).and_then(|_| stdout.flush()) {
+                // the reader of the output has stopped reading, like head
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            }
+        },
+    )
+    .is_some()
+}
+
+// Show the entries of a trashed directory with their sizes, return false if it can't be read
+pub fn list_element_entries(
+    connection: &Connection,
+    is_test: bool,
+    element_path: &ElementPath,
+    size_units: SizeUnits,
+) -> bool {
+    let children = read_element(
+        connection,
+        is_test,
+        element_path,
+        |stored_element, _trash_lock| {
+            let entries = stored_element.entries()?;
+            list_children(&entries, &element_path.inner_path).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "{} doesn't exist",
+                        display_inner_path(&stored_element.name, &element_path.inner_path)
+                    ),
+                )
+            })
+        },
+    );
+    let children = match children {
+        Some(children) => children,
+        None => return false,
+    };
+    for child in children {
+        let name = child.inner_path.rsplit('/').next().unwrap_or_default();
+        println!(
+            "{:>10}  {}",
+            format_size(child.size, size_units),
+            if child.is_dir {
+                format!("{}/", name).blue().bold()
+            } else if name.is_empty() {
+                // the element is a file
+                "".normal()
+            } else {
+                name.normal()
+            }
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arguments_manager::ArgumentsManager;
+    use crate::storage_manager::{self, StorageFormat};
+    use crate::structure_manager::{self, get_trash_directory_path};
+    use crate::trash_manager;
+
+    #[test]
+    fn test_extract_elements() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let trash_directory = get_trash_directory_path(is_test);
+        let directory = format!("{}{}extracted_directory", trash_directory, MAIN_SEPARATOR);
+        let destination = format!("{}{}copies", trash_directory, MAIN_SEPARATOR);
+
+        for compression in [false, true] {
+            config.compression = compression;
+            fs::create_dir_all(format!("{}{}src", directory, MAIN_SEPARATOR)).unwrap();
+            fs::write(
+                format!(
+                    "{}{}src{}main.rs",
+                    directory, MAIN_SEPARATOR, MAIN_SEPARATOR
+                ),
+                "fn main() {}",
+            )
+            .unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &directory,
+                &arguments_manager,
+            );
+            let trash_item = data_manager::find_all_trash_items(&connection, is_test)
+                .pop()
+                .unwrap();
+
+            assert!(extract_elements(
+                &connection,
+                is_test,
+                &[trash_item.id],
+                Some(&destination)
+            ));
+            let extracted_path = format!(
+                "{}{}extracted_directory{}src{}main.rs",
+                destination, MAIN_SEPARATOR, MAIN_SEPARATOR, MAIN_SEPARATOR
+            );
+            assert_eq!(fs::read_to_string(&extracted_path).unwrap(), "fn main() {}");
+            // the element stays in the trash
+            assert!(
+                data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).is_ok()
+            );
+            assert!(Path::new(&get_path_in_trash(&trash_item.hash, is_test)).exists());
+            remove_path(&destination);
+        }
+        assert!(!extract_elements(
+            &connection,
+            is_test,
+            &[-1],
+            Some(&destination)
+        ));
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_element_path() {
+        assert_eq!(
+            ElementPath::parse("4/src/main.rs/"),
+            Ok(ElementPath {
+                id: 4,
+                inner_path: "src/main.rs".to_string()
+            })
+        );
+        assert_eq!(ElementPath::parse("4").unwrap().inner_path, "");
+        assert!(ElementPath::parse("src/main.rs").is_err());
+    }
+
+    #[test]
+    fn test_read_stored_element() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let directory = format!(
+            "{}{}read_directory",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+
+        let encryption_key = EncryptionKey::new("read".to_string());
+        for (compression, encryption) in [(false, false), (true, false), (true, true)] {
+            config.compression = compression;
+            fs::create_dir_all(format!("{}/src/bin", directory)).unwrap();
+            fs::write(format!("{}/src/main.rs", directory), "fn main() {}").unwrap();
+            fs::write(format!("{}/src/bin/tool.rs", directory), "tool").unwrap();
+            fs::write(format!("{}/README.md", directory), "# rmt").unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &directory,
+                &arguments_manager,
+            );
+            let mut trash_item = data_manager::find_all_trash_items(&connection, is_test)
+                .pop()
+                .unwrap();
+            if encryption {
+                storage_manager::convert_element(
+                    &connection,
+                    &mut trash_item,
+                    StorageFormat::new(true, true, true),
+                    Some(&encryption_key),
+                    is_test,
+                )
+                .unwrap();
+            }
+            let mut stored_element =
+                StoredElement::open(&trash_item, Some(&encryption_key), is_test).unwrap();
+
+            let mut content = String::new();
+            stored_element
+                .open_file("src/main.rs")
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(content, "fn main() {}");
+            assert!(stored_element.open_file("src").is_err());
+            assert!(stored_element.open_file("missing.rs").is_err());
+
+            let entries = stored_element.entries().unwrap();
+            let children = list_children(&entries, "").unwrap();
+            assert_eq!(
+                children
+                    .iter()
+                    .map(|child| (child.inner_path.as_str(), child.size, child.is_dir))
+                    .collect::<Vec<_>>(),
+                vec![("README.md", 5, false), ("src", 16, true)]
+            );
+            assert_eq!(list_children(&entries, "src/bin").unwrap().len(), 1);
+            assert!(list_children(&entries, "missing").is_none());
+
+            drop(stored_element);
+            let decrypted_path = format!(
+                "{}.decrypted.zip",
+                get_path_in_trash(&trash_item.hash, is_test)
+            );
+            assert!(!Path::new(&decrypted_path).exists());
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_matches_glob() {
+        assert!(matches_glob("src/*.rs", "src/main.rs"));
+        assert!(!matches_glob("src/*.rs", "src/bin/tool.rs"));
+        assert!(matches_glob("src/**/*.rs", "src/main.rs"));
+        assert!(matches_glob("src/**/*.rs", "src/bin/tool.rs"));
+        assert!(matches_glob("**", "src/bin/tool.rs"));
+        assert!(matches_glob("README.?d", "README.md"));
+        assert!(!matches_glob("*", "src/main.rs"));
+        assert!(is_inside("src/main.rs", "src"));
+        assert!(!is_inside("srcs/main.rs", "src"));
+    }
+}
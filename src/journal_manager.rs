@@ -0,0 +1,391 @@
+// Adding an element to the trash or restoring it is done in several steps on the disk and in
+// the database. Before the first step, the intent is saved in the journal, so an operation
+// interrupted by a crash (or a ctrl-c) can be rolled back or finished at the next start.
+//
+// An operation copies the element from a source to a destination (the trash directory when
+// adding, the restore path when restoring), then removes the source:
+// - Started: the destination may be incomplete, the operation is rolled back.
+// - Copied: the destination is complete, the operation is finished.
+//
+// A conversion writes the element in its new storage format at the converted path (the source),
+// which then replaces the element in the trash (the destination). Removing files from a stored
+// directory (pruning) rewrites it the same way:
+// - Started: the converted element may be incomplete, it is removed.
+// - Copied: the converted element is complete, it replaces the element and the database is updated.
+
+use crate::storage_manager::{self, StorageFormat};
+use crate::{data_manager, trash_item::TrashItem};
+
+use colored::Colorize;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Add,
+    Restore,
+    Convert(StorageFormat),
+    Prune,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalState {
+    Started,
+    Copied,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub operation: Operation,
+    pub state: JournalState,
+    pub trash_item: TrashItem,
+    pub checksums: Vec<(String, String)>, // saved with the trash item when adding or pruning it
+    pub source_path: String,
+    pub destination_path: String,
+    pub temporary_path: Option<String>, // file used between the source and the destination
+}
+
+impl JournalEntry {
+    pub fn new(
+        operation: Operation,
+        trash_item: TrashItem,
+        source_path: String,
+        destination_path: String,
+    ) -> Self {
+        Self {
+            operation,
+            state: JournalState::Started,
+            trash_item,
+            checksums: Vec::new(),
+            source_path,
+            destination_path,
+            temporary_path: None,
+        }
+    }
+}
+
+fn to_yaml(journal_entry: &JournalEntry) -> String {
+    serde_yaml::to_string(journal_entry).expect("Unable to serialize the journal entry")
+}
+
+// Save the intent of the operation, return the id of the journal entry
+pub fn start(connection: &Connection, journal_entry: &JournalEntry, is_test: bool) -> i64 {
+    data_manager::insert_journal_entry(connection, &to_yaml(journal_entry), is_test)
+}
+
+// The destination is complete, from now on the operation will be finished if it is interrupted
+pub fn mark_copied(
+    connection: &Connection,
+    journal_id: i64,
+    journal_entry: &mut JournalEntry,
+    is_test: bool,
+) {
+    journal_entry.state = JournalState::Copied;
+    data_manager::update_journal_entry(connection, journal_id, &to_yaml(journal_entry), is_test);
+}
+
+// Save the result of the operation in the database and forget the journal entry, in one transaction
+pub fn finish(
+    connection: &Connection,
+    journal_id: i64,
+    journal_entry: &JournalEntry,
+    is_test: bool,
+) {
+    let transaction = connection
+        .unchecked_transaction()
+        .expect("Unable to start a transaction");
+    match journal_entry.operation {
+        Operation::Add => {
+            data_manager::insert_trash_item(&transaction, &journal_entry.trash_item, is_test);
+            data_manager::insert_checksums(
+                &transaction,
+                &journal_entry.trash_item.hash,
+                &journal_entry.checksums,
+                is_test,
+            );
+        }
+        Operation::Restore => data_manager::delete_trash_item_by_id(
+            &transaction,
+            is_test,
+            journal_entry.trash_item.id,
+        ),
+        Operation::Convert(_) => {
+            data_manager::update_trash_item_storage(
+                &transaction,
+                is_test,
+                &journal_entry.trash_item,
+            )
+            .expect("Unable to save the storage format of the element");
+        }
+        Operation::Prune => {
+            data_manager::update_trash_item_content(
+                &transaction,
+                is_test,
+                &journal_entry.trash_item,
+                &journal_entry.checksums,
+            )
+            .expect("Unable to save the content of the element");
+        }
+    }
+    data_manager::delete_journal_entry(&transaction, journal_id, is_test);
+    transaction
+        .commit()
+        .expect("Unable to commit the transaction");
+}
+
+pub fn remove_path(path: &str) {
+    let path = Path::new(path);
+    if path.is_dir() {
+        fs::remove_dir_all(path).unwrap();
+    } else if path.exists() {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+// "Add of /home/user/notes.txt to /home/user/.trash_rmt/<hash>", from the fields of an entry
+// which can still be read
+fn describe_unreadable_entry(yaml: &str) -> String {
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap_or_default();
+    let field = |name: &str| {
+        value
+            .get(name)
+            .and_then(|field| field.as_str())
+            .unwrap_or("?")
+            .to_string()
+    };
+    // a conversion is saved with its storage format, as !Convert
+    let operation = match value.get("operation") {
+        Some(serde_yaml::Value::Tagged(tagged)) => {
+            tagged.tag.to_string().trim_start_matches('!').to_string()
+        }
+        _ => field("operation"),
+    };
+    format!(
+        "{} of {} to {}",
+        operation,
+        field("source_path"),
+        field("destination_path")
+    )
+}
+
+// The entries which can't be read anymore, saved by another version of rmt for example, with a
+// description of their operation. They are kept until rmt --fsck forgets them.
+pub fn find_unreadable_entries(connection: &Connection, is_test: bool) -> Vec<(i64, String)> {
+    data_manager::find_all_journal_entries(connection, is_test)
+        .into_iter()
+        .filter(|(_, yaml)| serde_yaml::from_str::<JournalEntry>(yaml).is_err())
+        .map(|(journal_id, yaml)| (journal_id, describe_unreadable_entry(&yaml)))
+        .collect()
+}
+
+// Roll back or finish the operations interrupted during a previous run
+pub fn recover(connection: &Connection, is_test: bool) {
+    for (journal_id, yaml) in data_manager::find_all_journal_entries(connection, is_test) {
+        let mut journal_entry: JournalEntry = match serde_yaml::from_str(&yaml) {
+            Ok(journal_entry) => journal_entry,
+            Err(e) => {
+                println!(
+                    "{}: the interrupted operation {} ({}) can't be recovered ({}), run {} to forget it.",
+                    "Warning".red().bold(),
+                    journal_id,
+                    describe_unreadable_entry(&yaml).bold(),
+                    e,
+                    "rmt --fsck".bold()
+                );
+                continue;
+            }
+        };
+        if let Operation::Convert(_) | Operation::Prune = journal_entry.operation {
+            recover_conversion(connection, journal_id, &journal_entry, is_test);
+            continue;
+        }
+        let source_exists = Path::new(&journal_entry.source_path).exists();
+        let destination_exists = Path::new(&journal_entry.destination_path).exists();
+
+        // the source has been renamed to the destination, so the destination is complete
+        if journal_entry.state == JournalState::Started && !source_exists && destination_exists {
+            journal_entry.state = JournalState::Copied;
+        }
+
+        if let Some(temporary_path) = &journal_entry.temporary_path {
+            remove_path(temporary_path);
+        }
+
+        let name = journal_entry.trash_item.name.bold();
+        match journal_entry.state {
+            JournalState::Started if !source_exists => {
+                println!(
+                    "{}: {} has been lost during an interrupted operation.",
+                    "Warning".red().bold(),
+                    name
+                );
+                data_manager::delete_journal_entry(connection, journal_id, is_test);
+            }
+            JournalState::Started => {
+                remove_path(&journal_entry.destination_path);
+                data_manager::delete_journal_entry(connection, journal_id, is_test);
+                println!("An interrupted operation on {} has been rolled back.", name);
+            }
+            JournalState::Copied => {
+                remove_path(&journal_entry.source_path);
+                finish(connection, journal_id, &journal_entry, is_test);
+                println!("An interrupted operation on {} has been finished.", name);
+            }
+        }
+    }
+}
+
+fn recover_conversion(
+    connection: &Connection,
+    journal_id: i64,
+    journal_entry: &JournalEntry,
+    is_test: bool,
+) {
+    if let Some(temporary_path) = &journal_entry.temporary_path {
+        remove_path(temporary_path);
+    }
+
+    let name = journal_entry.trash_item.name.bold();
+    match journal_entry.state {
+        JournalState::Started => {
+            remove_path(&journal_entry.source_path);
+            data_manager::delete_journal_entry(connection, journal_id, is_test);
+            println!("An interrupted operation on {} has been rolled back.", name);
+        }
+        JournalState::Copied => {
+            match storage_manager::finish_rewrite(connection, journal_id, journal_entry, is_test) {
+                Ok(()) => println!("An interrupted operation on {} has been finished.", name),
+                Err(e) => println!(
+                    "{}: the interrupted operation on {} can't be finished yet ({}).",
+                    "Warning".red().bold(),
+                    name,
+                    e
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager::{self, get_path_in_trash, get_trash_directory_path};
+    use std::path::MAIN_SEPARATOR;
+
+    fn new_add_journal_entry(is_test: bool) -> JournalEntry {
+        let element_path = format!(
+            "{}{}journal.txt",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        let trash_item = TrashItem::new(
+            "journal.txt".to_string(),
+            sha256::digest("journal"),
+            get_trash_directory_path(is_test),
+            0,
+            3,
+            None,
+            false,
+            false,
+            false,
+        );
+        let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+        JournalEntry::new(Operation::Add, trash_item, element_path, path_in_trash)
+    }
+
+    #[test]
+    fn test_recover_started_add() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let journal_entry = new_add_journal_entry(is_test);
+        fs::write(&journal_entry.source_path, "rmt").unwrap();
+        // incomplete copy in the trash
+        fs::write(&journal_entry.destination_path, "r").unwrap();
+        start(&connection, &journal_entry, is_test);
+
+        recover(&connection, is_test);
+        assert!(Path::new(&journal_entry.source_path).exists());
+        assert!(!Path::new(&journal_entry.destination_path).exists());
+        assert!(data_manager::find_all_trash_items(&connection, is_test).is_empty());
+        assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_recover_copied_add() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let mut journal_entry = new_add_journal_entry(is_test);
+        fs::write(&journal_entry.source_path, "rmt").unwrap();
+        fs::write(&journal_entry.destination_path, "rmt").unwrap();
+        let journal_id = start(&connection, &journal_entry, is_test);
+        mark_copied(&connection, journal_id, &mut journal_entry, is_test);
+
+        recover(&connection, is_test);
+        assert!(!Path::new(&journal_entry.source_path).exists());
+        assert!(Path::new(&journal_entry.destination_path).exists());
+        let trash_items = data_manager::find_all_trash_items(&connection, is_test);
+        assert_eq!(trash_items.len(), 1);
+        assert_eq!(trash_items[0].hash, journal_entry.trash_item.hash);
+        assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_recover_renamed_add() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        // the element has been renamed to the trash, but the intent is still "started"
+        let journal_entry = new_add_journal_entry(is_test);
+        fs::write(&journal_entry.destination_path, "rmt").unwrap();
+        start(&connection, &journal_entry, is_test);
+
+        recover(&connection, is_test);
+        assert!(Path::new(&journal_entry.destination_path).exists());
+        assert_eq!(
+            data_manager::find_all_trash_items(&connection, is_test).len(),
+            1
+        );
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_recover_unreadable_entry() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        // saved by an older version of rmt, when the date was a string
+        let yaml = to_yaml(&new_add_journal_entry(is_test))
+            .replace("date: 0", "date: 2023-04-01 18:30:00");
+        let journal_id = data_manager::insert_journal_entry(&connection, &yaml, is_test);
+
+        recover(&connection, is_test);
+        let unreadable_entries = find_unreadable_entries(&connection, is_test);
+        assert_eq!(unreadable_entries.len(), 1);
+        assert_eq!(unreadable_entries[0].0, journal_id);
+        assert!(unreadable_entries[0].1.starts_with("Add of "));
+        assert!(unreadable_entries[0].1.contains("journal.txt to "));
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
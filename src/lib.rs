@@ -1,11 +1,27 @@
+pub mod agent_manager;
 pub mod argument_errors;
 pub mod arguments_manager;
+pub mod checksum_manager;
 pub mod config;
 pub mod config_manager;
 pub mod data_manager;
 pub mod database_errors;
+pub mod diff_manager;
 pub mod display_manager;
+pub mod encryption_manager;
+pub mod free_space_manager;
+pub mod fsck_manager;
+pub mod grep_manager;
 pub mod input_manager;
+pub mod inspect_manager;
+pub mod journal_manager;
+pub mod lock_manager;
+pub mod prompt_manager;
+pub mod query_errors;
+pub mod query_manager;
+pub mod restore_manager;
+pub mod storage_manager;
 pub mod structure_manager;
 pub mod trash_item;
 pub mod trash_manager;
+pub mod version_manager;
@@ -0,0 +1,70 @@
+// Several rmt processes can run at the same time (two shells, a cron job next to the TUI...).
+// Every operation that changes the trash directory or the database holds an advisory lock on a
+// file of the trash, so they are done one after the other. The lock is released when the guard
+// is dropped, or by the system if the process dies.
+//
+// The lock isn't reentrant: a function holding it must not call another function taking it.
+
+use crate::structure_manager::get_lock_path;
+
+use colored::Colorize;
+use std::fs::{File, OpenOptions, TryLockError};
+
+pub struct TrashLock {
+    file: File,
+}
+
+impl Drop for TrashLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+// Wait until no other rmt process is changing the trash, then take the lock
+pub fn lock_trash(is_test: bool) -> TrashLock {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(get_lock_path(is_test))
+        .expect("Unable to open the lock file of the trash");
+
+    match file.try_lock() {
+        Ok(_) => (),
+        Err(TryLockError::WouldBlock) => {
+            println!(
+                "{}",
+                "Waiting for another rmt process to finish...\r".bold()
+            );
+            file.lock().expect("Unable to lock the trash");
+        }
+        Err(TryLockError::Error(_)) => {
+            file.lock().expect("Unable to lock the trash");
+        }
+    }
+    TrashLock { file }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager;
+
+    #[test]
+    fn test_lock_trash() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+
+        let trash_lock = lock_trash(is_test);
+        let other_file = File::open(get_lock_path(is_test)).unwrap();
+        assert!(matches!(
+            other_file.try_lock(),
+            Err(TryLockError::WouldBlock)
+        ));
+
+        drop(trash_lock);
+        assert!(other_file.try_lock().is_ok());
+
+        structure_manager::clear_structure(is_test);
+    }
+}
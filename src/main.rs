@@ -3,6 +3,8 @@ use rmt_lib::*;
 use arguments_manager::ArgumentsManager;
 use clap::Parser;
 use colored::Colorize;
+use data_manager::TrashItemsOrder;
+use restore_manager::RestoreOptions;
 
 use crate::argument_errors::RmtArgumentErrors;
 
@@ -11,19 +13,20 @@ fn main() {
     let mut arguments_manager = ArgumentsManager::parse();
     let (config, connection) = structure_manager::setup_structure(arguments_manager.is_test);
 
-    if arguments_manager.elements.is_empty()
-        && !arguments_manager.is_trash_flush
-        && !arguments_manager.is_trash_display
-        && !arguments_manager.is_trash_info
-    {
+    if arguments_manager.elements.is_empty() && !arguments_manager.has_trash_command() {
         println!("{}", RmtArgumentErrors::InvalidNumberOfArguments(0));
         std::process::exit(1);
     }
 
-    let exit_code = arguments_manager.filter_all_errors();
+    let mut exit_code = arguments_manager.filter_all_errors();
 
     if arguments_manager.is_trash_display {
-        input_manager::start_display(&connection, arguments_manager.is_test);
+        input_manager::start_display(
+            &connection,
+            &config,
+            arguments_manager.is_test,
+            &RestoreOptions::new(&arguments_manager),
+        );
     } else if arguments_manager.is_trash_flush {
         let message = format!(
             "Are you sure to {} all the elements of your trash ?",
@@ -33,7 +36,210 @@ fn main() {
             trash_manager::remove_all_elements(&connection, arguments_manager.is_test);
         }
     } else if arguments_manager.is_trash_info {
-        trash_manager::display_trash_information(&connection, arguments_manager.is_test);
+        trash_manager::display_trash_information(
+            &connection,
+            arguments_manager.is_test,
+            arguments_manager.get_size_units(),
+            config.date_format,
+            arguments_manager.is_json,
+        );
+    } else if arguments_manager.is_agent_serve {
+        agent_manager::serve_agent_from_stdin(
+            arguments_manager.is_test,
+            arguments_manager.agent_timeout,
+        );
+    } else if arguments_manager.is_agent {
+        if !agent_manager::start_agent(
+            &connection,
+            arguments_manager.is_test,
+            arguments_manager.agent_timeout,
+        ) {
+            exit_code = 1;
+        }
+    } else if arguments_manager.is_agent_stop {
+        agent_manager::stop_agent(arguments_manager.is_test);
+    } else if let Some(trash_items_ids) = &arguments_manager.rekey {
+        if !trash_manager::rekey_elements(&connection, arguments_manager.is_test, trash_items_ids) {
+            exit_code = 1;
+        }
+    } else if let Some(trash_items_ids) = &arguments_manager.verify {
+        if !checksum_manager::verify_elements(
+            &connection,
+            arguments_manager.is_test,
+            trash_items_ids,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(query) = &arguments_manager.search {
+        if !trash_manager::list_elements(
+            &connection,
+            arguments_manager.is_test,
+            &query.join(" "),
+            TrashItemsOrder::Relevance,
+            config.date_format,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(query) = &arguments_manager.list {
+        if !trash_manager::list_elements(
+            &connection,
+            arguments_manager.is_test,
+            &query.join(" "),
+            TrashItemsOrder::Newest,
+            config.date_format,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(query) = &arguments_manager.purge {
+        if !trash_manager::purge_elements(
+            &connection,
+            arguments_manager.is_test,
+            &query.join(" "),
+            config.date_format,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(query) = &arguments_manager.restore {
+        if !trash_manager::restore_queried_elements(
+            &connection,
+            &config,
+            arguments_manager.is_test,
+            &query.join(" "),
+            &RestoreOptions::new(&arguments_manager),
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(element_paths) = &arguments_manager.restore_files {
+        if !restore_manager::restore_element_files(
+            &connection,
+            &config,
+            arguments_manager.is_test,
+            element_paths,
+            &RestoreOptions::new(&arguments_manager),
+            arguments_manager.is_pruning,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(trash_items_ids) = &arguments_manager.extract {
+        if !inspect_manager::extract_elements(
+            &connection,
+            arguments_manager.is_test,
+            trash_items_ids,
+            arguments_manager.destination.as_deref(),
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(element_path) = &arguments_manager.cat {
+        if !inspect_manager::cat_element(&connection, arguments_manager.is_test, element_path) {
+            exit_code = 1;
+        }
+    } else if let Some(element_path) = &arguments_manager.ls {
+        if !inspect_manager::list_element_entries(
+            &connection,
+            arguments_manager.is_test,
+            element_path,
+            arguments_manager.get_size_units(),
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(diff) = &arguments_manager.diff {
+        match inspect_manager::ElementPath::parse(&diff[0]) {
+            Ok(element_path) => {
+                if !diff_manager::diff_element(
+                    &connection,
+                    arguments_manager.is_test,
+                    &element_path,
+                    diff.get(1).map(|path| path.as_str()),
+                ) {
+                    exit_code = 1;
+                }
+            }
+            Err(e) => {
+                println!("{}: {}", "Error".red().bold(), e);
+                exit_code = 1;
+            }
+        }
+    } else if let Some(pattern) = &arguments_manager.grep {
+        if !grep_manager::grep_elements(
+            &connection,
+            arguments_manager.is_test,
+            pattern,
+            arguments_manager.is_decrypting,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(path) = &arguments_manager.versions {
+        let is_success = match arguments_manager.restore_version {
+            Some(version) => version_manager::restore_version(
+                &connection,
+                &config,
+                arguments_manager.is_test,
+                path,
+                version,
+                &RestoreOptions::new(&arguments_manager),
+            ),
+            None => version_manager::display_versions(
+                &connection,
+                arguments_manager.is_test,
+                path,
+                config.date_format,
+                arguments_manager.get_size_units(),
+            ),
+        };
+        if !is_success {
+            exit_code = 1;
+        }
+    } else if let Some(count) = arguments_manager.top {
+        if !trash_manager::display_top_elements(
+            &connection,
+            arguments_manager.is_test,
+            count,
+            arguments_manager.is_grouped,
+            arguments_manager.get_size_units(),
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(trash_items_ids) = &arguments_manager.pin {
+        if !trash_manager::pin_elements(
+            &connection,
+            arguments_manager.is_test,
+            trash_items_ids,
+            true,
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(trash_items_ids) = &arguments_manager.unpin {
+        if !trash_manager::pin_elements(
+            &connection,
+            arguments_manager.is_test,
+            trash_items_ids,
+            false,
+        ) {
+            exit_code = 1;
+        }
+    } else if arguments_manager.is_gc {
+        if !storage_manager::collect_garbage(
+            &connection,
+            config.cold_storage,
+            arguments_manager.is_test,
+            arguments_manager.get_size_units(),
+        ) {
+            exit_code = 1;
+        }
+    } else if let Some(trash_items_ids) = &arguments_manager.convert {
+        if !storage_manager::convert_to_config(
+            &connection,
+            &config,
+            arguments_manager.is_test,
+            trash_items_ids,
+            arguments_manager.get_size_units(),
+        ) {
+            exit_code = 1;
+        }
+    } else if arguments_manager.is_fsck {
+        if !fsck_manager::check_trash(&connection, arguments_manager.is_test) {
+            exit_code = 1;
+        }
     } else {
         trash_manager::add_all_elements_to_trash(
             &connection,
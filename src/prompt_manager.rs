@@ -0,0 +1,185 @@
+// Line editor asking for the directory where an element is restored. Tab only completes the
+// directories, the problems of the typed path are shown while typing and Enter is refused until
+// it is fixed, and the previous answers are kept in a history file of the trash (Up, Down, Ctrl-R).
+
+use crate::restore_manager;
+use crate::structure_manager::{get_home_directory_path, get_restore_history_path};
+
+use colored::Colorize;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::{Hint, Hinter};
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal};
+use std::path::{Path, MAIN_SEPARATOR};
+
+struct DirectoryHelper {
+    completer: FilenameCompleter,
+    element_name: String, // name of the restored element, it mustn't exist in the directory
+    is_terminal: bool,    // without a terminal, a refused line would be joined to the next one
+}
+
+// Shown after the typed path, never inserted in the line
+struct DirectoryHint(String);
+
+impl Hint for DirectoryHint {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl Completer for DirectoryHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.completer.complete_path(line, pos)?;
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .filter(|candidate| candidate.replacement.ends_with(MAIN_SEPARATOR))
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for DirectoryHelper {
+    type Hint = DirectoryHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<DirectoryHint> {
+        if pos < line.len() || line.is_empty() {
+            return None;
+        }
+        check_directory(line, &self.element_name)
+            .err()
+            .map(|problem| DirectoryHint(format!("  ({})", problem)))
+    }
+}
+
+impl Highlighter for DirectoryHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.red().dimmed().to_string())
+    }
+}
+
+impl Validator for DirectoryHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if !self.is_terminal {
+            return Ok(ValidationResult::Valid(None));
+        }
+        Ok(match check_directory(ctx.input(), &self.element_name) {
+            Ok(_) => ValidationResult::Valid(None),
+            Err(problem) => ValidationResult::Invalid(Some(format!("  ({})", problem))),
+        })
+    }
+}
+
+impl Helper for DirectoryHelper {}
+
+// Return the absolute path of the directory if the element can be restored in it
+fn check_directory(line: &str, element_name: &str) -> Result<String, String> {
+    if line.trim().is_empty() {
+        return Err("type the path of a directory".to_string());
+    }
+    let directory = restore_manager::to_absolute_path(line);
+    if !Path::new(&directory).exists() {
+        Err(format!("{} doesn't exist", directory))
+    } else if !Path::new(&directory).is_dir() {
+        Err(format!("{} isn't a directory", directory))
+    } else if Path::new(&directory).join(element_name).exists() {
+        Err(format!("{} already contains {}", directory, element_name))
+    } else {
+        Ok(directory)
+    }
+}
+
+// Ask the directory where the element is restored, None if the user gives up (Ctrl-C or Ctrl-D)
+pub fn ask_restore_directory(element_name: &str, is_test: bool) -> Option<String> {
+    println!(
+        "{}\r",
+        "Please enter the directory where your element is restored (Tab to complete, Ctrl-C to leave it in the trash)".bold()
+    );
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor: Editor<DirectoryHelper, DefaultHistory> = match Editor::with_config(config) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!(
+                "{}: unable to start the prompt ({}).\r",
+                "Error".red().bold(),
+                e
+            );
+            return None;
+        }
+    };
+    editor.set_helper(Some(DirectoryHelper {
+        completer: FilenameCompleter::new(),
+        element_name: element_name.to_string(),
+        is_terminal: io::stdin().is_terminal(),
+    }));
+    let history_path = get_restore_history_path(is_test);
+    // there is no history before the first answer
+    let _ = editor.load_history(&history_path);
+
+    let initial_line = format!("{}{}", get_home_directory_path(), MAIN_SEPARATOR);
+    let prompt = format!("{} ", ">>".green().bold());
+    loop {
+        match editor.readline_with_initial(&prompt, (&initial_line, "")) {
+            // checked again when the input isn't a terminal
+            Ok(line) => match check_directory(&line, element_name) {
+                Ok(directory) => {
+                    let _ = editor.add_history_entry(directory.as_str());
+                    let _ = editor.save_history(&history_path);
+                    return Some(directory);
+                }
+                Err(problem) => println!("{}\r", problem.yellow()),
+            },
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return None,
+            Err(e) => {
+                println!("{}: {}\r", "Error".red().bold(), e);
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager::{self, get_trash_directory_path};
+    use std::fs;
+
+    #[test]
+    fn test_check_directory() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+        let directory = get_trash_directory_path(is_test);
+        let file_path = format!("{}{}notes.txt", directory, MAIN_SEPARATOR);
+        fs::write(&file_path, "rmt").unwrap();
+
+        assert_eq!(
+            check_directory(&format!("{}{}", directory, MAIN_SEPARATOR), "report.pdf"),
+            Ok(directory.clone())
+        );
+        assert!(check_directory(&directory, "notes.txt").is_err());
+        assert!(check_directory(&file_path, "report.pdf").is_err());
+        assert!(check_directory(&format!("{}-missing", directory), "report.pdf").is_err());
+        assert!(check_directory("  ", "report.pdf").is_err());
+
+        structure_manager::clear_structure(is_test);
+    }
+}
@@ -0,0 +1,57 @@
+use core::fmt;
+use std::error::Error;
+
+use colored::Colorize;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RmtQueryErrors {
+    UnknownKey(String),
+    InvalidOperator { key: String, operator: String },
+    InvalidValue { key: String, value: String },
+    UnclosedQuote,
+}
+
+impl RmtQueryErrors {
+    // The message alone, short enough for the filter bar of the GUI
+    pub fn error_message(&self) -> String {
+        match self {
+            RmtQueryErrors::UnknownKey(key) => format!(
+                "{} is not a valid key (path, name, size, type, age).",
+                key.red().bold()
+            ),
+            RmtQueryErrors::InvalidOperator { key, operator } => format!(
+                "The operator {} can't be used with {}.",
+                operator.red().bold(),
+                key.green().bold()
+            ),
+            RmtQueryErrors::InvalidValue { key, value } => format!(
+                "{} is not a valid value for {}.",
+                value.red().bold(),
+                key.green().bold()
+            ),
+            RmtQueryErrors::UnclosedQuote => "A quote is not closed.".to_string(),
+        }
+    }
+
+    fn default_help_message() -> String {
+        format!(
+            "A query looks like {}, use the {} option to get more details.",
+            "path:~/work size>100M type:dir age<7d name:*.log".bold(),
+            "rmt --help".bold().green()
+        )
+    }
+}
+
+impl fmt::Display for RmtQueryErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}\n{}\n",
+            "Error".red().bold(),
+            self.error_message(),
+            RmtQueryErrors::default_help_message().italic()
+        )
+    }
+}
+
+impl Error for RmtQueryErrors {}
@@ -0,0 +1,347 @@
+// A small query language to select elements of the trash, shared by --list, --purge, --restore,
+// --search and the filter of the GUI:
+//
+//   path:~/work size>100M type:dir age<7d name:*.log report
+//
+// - path:<dir>          elements trashed from this directory or one of its subdirectories
+// - name:<glob>         name matching the glob (* and ?), case sensitive
+// - size<op><size>      size in bytes, with an optional unit K, M, G or T (powers of 1024)
+// - type:dir|file
+// - age<op><duration>   time since the element was trashed, unit s, m (minutes), h, d or w
+// - any other word is searched in the names and paths of the elements (see data_manager::to_search_query)
+//
+// <op> is one of : = < > <= >=, values with spaces can be written between double quotes.
+
+use crate::query_errors::RmtQueryErrors;
+use crate::restore_manager::to_absolute_path;
+
+use rusqlite::types::Value;
+use std::path::MAIN_SEPARATOR;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn parse(operator: &str) -> Option<Self> {
+        match operator {
+            ":" | "=" => Some(Comparison::Equal),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None,
+        }
+    }
+
+    fn to_sql(self) -> &'static str {
+        match self {
+            Comparison::Equal => "=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Path(String),
+    Name(String),
+    Size(Comparison, u64),
+    IsFolder(bool),
+    Age(Comparison, u64), // in seconds
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub words: Vec<String>,
+    pub conditions: Vec<Condition>,
+}
+
+const KEYS: [&str; 5] = ["path", "name", "size", "type", "age"];
+const OPERATORS: [&str; 6] = ["<=", ">=", ":", "=", "<", ">"];
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, RmtQueryErrors> {
+        let mut parsed_query = Query::default();
+        for token in split_tokens(query)? {
+            match split_condition(&token) {
+                Some((key, operator, value)) => parsed_query
+                    .conditions
+                    .push(parse_condition(key, operator, value)?),
+                None => parsed_query.words.push(token),
+            }
+        }
+        Ok(parsed_query)
+    }
+
+    // The words searched with the full-text index
+    pub fn get_search(&self) -> String {
+        self.words.join(" ")
+    }
+
+    // The SQL condition on the trash table (named trash_item) selecting the elements, the values
+    // are added to the parameters of the statement
+    pub fn to_sql(&self, parameters: &mut Vec<Value>) -> String {
+        let mut clauses = Vec::new();
+        for condition in self.conditions.iter() {
+            let clause = match condition {
+                Condition::Path(path) => {
+                    let path = path.trim_end_matches(MAIN_SEPARATOR);
+                    // every element is under the root
+                    if path.is_empty() {
+                        continue;
+                    }
+                    parameters.push(Value::Text(path.to_string()));
+                    let index = parameters.len();
+                    format!(
+                        "(trash_item.path = ?{0} OR substr(trash_item.path, 1, length(?{0}) + 1) = ?{0} || '{1}')",
+                        index, MAIN_SEPARATOR
+                    )
+                }
+                Condition::Name(glob) => {
+                    parameters.push(Value::Text(glob.clone()));
+                    format!("trash_item.name GLOB ?{}", parameters.len())
+                }
+                Condition::Size(comparison, size) => {
+                    parameters.push(Value::Integer(i64::try_from(*size).unwrap_or(i64::MAX)));
+                    format!(
+                        "trash_item.real_size {} ?{}",
+                        comparison.to_sql(),
+                        parameters.len()
+                    )
+                }
+                Condition::IsFolder(is_folder) => {
+                    parameters.push(Value::Integer(*is_folder as i64));
+                    format!("trash_item.is_folder = ?{}", parameters.len())
+                }
+                Condition::Age(comparison, seconds) => {
+                    parameters.push(Value::Integer(i64::try_from(*seconds).unwrap_or(i64::MAX)));
+                    format!(
+                        "(CAST(strftime('%s', 'now') AS INTEGER) - trash_item.date) {} ?{}",
+                        comparison.to_sql(),
+                        parameters.len()
+                    )
+                }
+            };
+            clauses.push(clause);
+        }
+        if clauses.is_empty() {
+            "1".to_string()
+        } else {
+            clauses.join(" AND ")
+        }
+    }
+}
+
+// Split the query on the whitespaces which are not between double quotes
+fn split_tokens(query: &str) -> Result<Vec<String>, RmtQueryErrors> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut is_quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => is_quoted = !is_quoted,
+            c if c.is_whitespace() && !is_quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if is_quoted {
+        return Err(RmtQueryErrors::UnclosedQuote);
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// "size>=10M" -> ("size", ">=", "10M"), None if the token is a simple word
+fn split_condition(token: &str) -> Option<(&str, &str, &str)> {
+    let key_end = token.find(|c: char| !c.is_ascii_alphabetic())?;
+    if key_end == 0 {
+        return None;
+    }
+    let (key, rest) = token.split_at(key_end);
+    let operator = OPERATORS
+        .iter()
+        .find(|operator| rest.starts_with(*operator))?;
+    Some((key, operator, &rest[operator.len()..]))
+}
+
+fn parse_condition(key: &str, operator: &str, value: &str) -> Result<Condition, RmtQueryErrors> {
+    if !KEYS.contains(&key) {
+        return Err(RmtQueryErrors::UnknownKey(key.to_string()));
+    }
+    let comparison = Comparison::parse(operator).unwrap();
+    let invalid_value = || RmtQueryErrors::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    if comparison != Comparison::Equal && !["size", "age"].contains(&key) {
+        return Err(RmtQueryErrors::InvalidOperator {
+            key: key.to_string(),
+            operator: operator.to_string(),
+        });
+    }
+    if value.is_empty() {
+        return Err(invalid_value());
+    }
+
+    match key {
+        // the paths are saved absolute in the database, without the last separator
+        "path" => Ok(Condition::Path(to_absolute_path(value))),
+        "name" => Ok(Condition::Name(value.to_string())),
+        "size" => parse_with_unit(
+            value,
+            &[
+                ("", 1),
+                ("K", 1 << 10),
+                ("M", 1 << 20),
+                ("G", 1 << 30),
+                ("T", 1 << 40),
+            ],
+        )
+        .map(|size| Condition::Size(comparison, size))
+        .ok_or_else(invalid_value),
+        "type" => match value {
+            "dir" | "directory" | "folder" => Ok(Condition::IsFolder(true)),
+            "file" => Ok(Condition::IsFolder(false)),
+            _ => Err(invalid_value()),
+        },
+        _ => parse_with_unit(
+            value,
+            &[
+                ("s", 1),
+                ("m", 60),
+                ("h", 3600),
+                ("d", 86400),
+                ("w", 604800),
+            ],
+        )
+        .map(|seconds| Condition::Age(comparison, seconds))
+        .ok_or_else(invalid_value),
+    }
+}
+
+// "100M" -> 100 * 1024 * 1024, the units are case insensitive and a decimal number is accepted.
+// The value has to fit in an SQLite integer.
+fn parse_with_unit(value: &str, units: &[(&str, u64)]) -> Option<u64> {
+    let number_end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_end);
+    let number: f64 = number.parse().ok()?;
+    let (_, multiplier) = units
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit))?;
+    let value = number * *multiplier as f64;
+    // i64::MAX as f64 is rounded up to 2^63, which doesn't fit
+    if value.is_finite() && value < i64::MAX as f64 {
+        Some(value as u64)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structure_manager::get_home_directory_path;
+
+    #[test]
+    fn test_parse_query() {
+        let query =
+            Query::parse("path:~/work size>100M type:dir age<7d name:*.log report").unwrap();
+        assert_eq!(query.words, vec!["report".to_string()]);
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Path(format!("{}/work", get_home_directory_path())),
+                Condition::Size(Comparison::Greater, 100 * 1024 * 1024),
+                Condition::IsFolder(true),
+                Condition::Age(Comparison::Less, 7 * 86400),
+                Condition::Name("*.log".to_string()),
+            ]
+        );
+
+        let query = Query::parse("\"name:my file.txt\" size<=1.5k").unwrap();
+        assert_eq!(
+            query.conditions,
+            vec![
+                Condition::Name("my file.txt".to_string()),
+                Condition::Size(Comparison::LessOrEqual, 1536),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_condition() {
+        assert_eq!(
+            Query::parse("path:/tmp/").unwrap().conditions,
+            vec![Condition::Path("/tmp".to_string())]
+        );
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE trash_item (path TEXT);
+                 INSERT INTO trash_item VALUES ('/'), ('/tmp'), ('/tmp/work'), ('/tmpfs'), ('/home');",
+            )
+            .unwrap();
+        let count_matches = |query: &str| -> i64 {
+            let mut parameters = Vec::new();
+            let condition = Query::parse(query).unwrap().to_sql(&mut parameters);
+            connection
+                .query_row(
+                    &format!("SELECT count(*) FROM trash_item WHERE {}", condition),
+                    rusqlite::params_from_iter(parameters),
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(count_matches("path:/"), 5);
+        assert_eq!(count_matches("path:/tmp/"), 2);
+        assert_eq!(count_matches("path:/tmp"), 2);
+    }
+
+    #[test]
+    fn test_parse_invalid_query() {
+        assert_eq!(
+            Query::parse("sise>10M"),
+            Err(RmtQueryErrors::UnknownKey("sise".to_string()))
+        );
+        assert_eq!(
+            Query::parse("type>dir"),
+            Err(RmtQueryErrors::InvalidOperator {
+                key: "type".to_string(),
+                operator: ">".to_string()
+            })
+        );
+        assert_eq!(
+            Query::parse("age<7y"),
+            Err(RmtQueryErrors::InvalidValue {
+                key: "age".to_string(),
+                value: "7y".to_string()
+            })
+        );
+        assert_eq!(
+            Query::parse("size>10000000000000000000"),
+            Err(RmtQueryErrors::InvalidValue {
+                key: "size".to_string(),
+                value: "10000000000000000000".to_string()
+            })
+        );
+        assert!(Query::parse("age>100000000000000000000w").is_err());
+        assert_eq!(Query::parse("name:\"a"), Err(RmtQueryErrors::UnclosedQuote));
+    }
+}
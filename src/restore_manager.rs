@@ -0,0 +1,1170 @@
+// Where a restored element goes. It is restored at its original path when it is free, otherwise
+// the conflict policy (rmt --on-conflict) decides what to do. When the original directory doesn't
+// exist anymore, it can be recreated (rmt --recreate-parents) with the permissions, owner and
+// modification time its directories had when the element was trashed. The elements can also be
+// restored somewhere else, in a single directory (rmt --to) or by replacing the beginning of their
+// path (rmt --map /old=/new); these directories are created when they are missing. Some files of
+// a trashed directory can be restored on their own (rmt --restore-files), and removed from the
+// stored directory (rmt --prune).
+
+use crate::arguments_manager::ArgumentsManager;
+use crate::checksum_manager;
+use crate::config::Config;
+use crate::data_manager;
+use crate::display_manager;
+use crate::inspect_manager::{self, ElementPath, Entry, StoredElement};
+use crate::lock_manager;
+use crate::prompt_manager;
+use crate::storage_manager;
+use crate::trash_item::TrashItem;
+use crate::trash_manager;
+
+use clap::ValueEnum;
+use colored::Colorize;
+use rusqlite::Connection;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, MAIN_SEPARATOR};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    Rename,    // keep both, the restored element is renamed "name (1)"
+    Overwrite, // the existing element is moved to the trash
+    Merge,     // the content of the restored directory is added to the existing directory
+    Skip,      // the element stays in the trash
+    #[default]
+    Prompt, // ask for another directory
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RestoreOptions {
+    pub on_conflict: ConflictPolicy,
+    pub is_recreating_parents: bool,
+    pub destination: Option<String>, // directory receiving every restored element
+    pub path_maps: Vec<PathMap>,
+}
+
+impl RestoreOptions {
+    pub fn new(arguments_manager: &ArgumentsManager) -> Self {
+        Self {
+            on_conflict: arguments_manager.on_conflict,
+            is_recreating_parents: arguments_manager.is_recreating_parents,
+            destination: arguments_manager
+                .destination
+                .as_deref()
+                .map(to_absolute_path),
+            path_maps: arguments_manager.path_maps.clone(),
+        }
+    }
+
+    // The directory where the element is restored, and if it has been chosen by the user
+    pub fn get_restore_directory(&self, trash_item: &TrashItem) -> (String, bool) {
+        if let Some(destination) = &self.destination {
+            return (destination.clone(), true);
+        }
+        // the longest prefix is the most precise one
+        self.path_maps
+            .iter()
+            .filter_map(|path_map| path_map.apply(&trash_item.path))
+            .max_by_key(|(_, prefix_length)| *prefix_length)
+            .map_or((trash_item.path.clone(), false), |(path, _)| (path, true))
+    }
+}
+
+// rmt --map /old/prefix=/new/prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMap {
+    pub from: String,
+    pub to: String,
+}
+
+impl PathMap {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.split_once('=') {
+            Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok(Self {
+                from: to_absolute_path(from),
+                to: to_absolute_path(to),
+            }),
+            _ => Err(format!(
+                "'{}' isn't a valid map, it should look like /old/prefix=/new/prefix",
+                value
+            )),
+        }
+    }
+
+    // Replace the prefix of the path, only on whole directory names: /home/al doesn't match /home/alice
+    fn apply(&self, path: &str) -> Option<(String, usize)> {
+        let rest = path.strip_prefix(&self.from)?;
+        if !rest.is_empty()
+            && !rest.starts_with(MAIN_SEPARATOR)
+            && !self.from.ends_with(MAIN_SEPARATOR)
+        {
+            return None;
+        }
+        Some((
+            format!("{}{}", self.to.trim_end_matches(MAIN_SEPARATOR), rest),
+            self.from.len(),
+        ))
+    }
+}
+
+// Expand ~ and the variables, and make the path absolute without requiring it to exist
+pub fn to_absolute_path(path: &str) -> String {
+    let expanded_path = shellexpand::full(path)
+        .map(|path| path.into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    let absolute_path = std::env::current_dir()
+        .map(|directory| directory.join(&expanded_path))
+        .unwrap_or_else(|_| expanded_path.into());
+    let absolute_path = absolute_path.to_string_lossy();
+    match absolute_path.trim_end_matches(MAIN_SEPARATOR) {
+        "" => MAIN_SEPARATOR.to_string(),
+        absolute_path => absolute_path.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryMetadata {
+    pub path: String,
+    pub mode: u32,
+    pub modified: i64, // in seconds since the epoch
+    pub uid: u32,
+    pub gid: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RestoreTarget {
+    pub restored_path: String,
+    pub merge_path: Option<String>, // directory receiving the content of the restored element
+    pub created_directories: Vec<String>, // parents recreated for the element, the highest first
+}
+
+impl RestoreTarget {
+    fn new(restored_path: String) -> Self {
+        Self {
+            restored_path,
+            merge_path: None,
+            created_directories: Vec::new(),
+        }
+    }
+}
+
+fn join_path(directory: &str, name: &str) -> String {
+    format!("{}{}{}", directory, MAIN_SEPARATOR, name)
+}
+
+// Find where to restore the element, None if it has to stay in the trash
+pub fn resolve_restore_target(
+    connection: &Connection,
+    config: &Config,
+    trash_item: &TrashItem,
+    restore_options: &RestoreOptions,
+    is_test: bool,
+) -> Option<RestoreTarget> {
+    let (directory, is_chosen) = restore_options.get_restore_directory(trash_item);
+    let initial_path = join_path(&directory, &trash_item.name);
+    if !Path::new(&directory).exists() && (is_chosen || restore_options.is_recreating_parents) {
+        match create_parent_directories(&directory) {
+            Ok(created_directories) => {
+                return Some(RestoreTarget {
+                    created_directories,
+                    ..RestoreTarget::new(initial_path)
+                })
+            }
+            Err(e) => println!(
+                "{}: unable to create {} ({}).\r",
+                "Error".red().bold(),
+                directory.green().bold(),
+                e
+            ),
+        }
+    }
+    if is_chosen && !Path::new(&directory).is_dir() {
+        println!(
+            "{} is skipped, {} isn't a directory.\r",
+            trash_item.name.green().bold(),
+            directory.green().bold()
+        );
+        return None;
+    }
+    if !Path::new(&directory).is_dir() {
+        println!(
+            "Unfortunately Path {} doesn't exist anymore, so we can't restore your element to the original path :c\r",
+            directory.green().bold()
+        );
+        return prompt_manager::ask_restore_directory(&trash_item.name, is_test)
+            .map(|directory| RestoreTarget::new(join_path(&directory, &trash_item.name)));
+    }
+    if !Path::new(&initial_path).exists() {
+        return Some(RestoreTarget::new(initial_path));
+    }
+
+    // the user isn't asked for every element of a restore in another directory
+    let on_conflict = match restore_options.on_conflict {
+        ConflictPolicy::Prompt if is_chosen => ConflictPolicy::Rename,
+        on_conflict => on_conflict,
+    };
+    match on_conflict {
+        ConflictPolicy::Overwrite => {
+            let arguments_manager = ArgumentsManager {
+                is_test,
+                ..Default::default()
+            };
+            trash_manager::add_element_to_trash(
+                connection,
+                config,
+                &initial_path,
+                &arguments_manager,
+            );
+            println!(
+                "The existing {} has been moved to the trash.\r",
+                initial_path.green().bold()
+            );
+            Some(RestoreTarget::new(initial_path))
+        }
+        ConflictPolicy::Merge if trash_item.is_folder && Path::new(&initial_path).is_dir() => {
+            Some(RestoreTarget {
+                merge_path: Some(initial_path),
+                ..RestoreTarget::new(get_free_path(&directory, &trash_item.name, true))
+            })
+        }
+        ConflictPolicy::Merge => {
+            let restored_path = get_free_path(&directory, &trash_item.name, trash_item.is_folder);
+            println!(
+                "{} can only be merged with a directory, it is restored as {}.\r",
+                initial_path.green().bold(),
+                restored_path.green().bold()
+            );
+            Some(RestoreTarget::new(restored_path))
+        }
+        ConflictPolicy::Skip => {
+            println!(
+                "{} is skipped, {} already exists.\r",
+                trash_item.name.green().bold(),
+                initial_path.green().bold()
+            );
+            None
+        }
+        ConflictPolicy::Rename => Some(RestoreTarget::new(get_free_path(
+            &directory,
+            &trash_item.name,
+            trash_item.is_folder,
+        ))),
+        ConflictPolicy::Prompt => {
+            println!(
+                "There is already an element at {}, so we can't restore your element to the original path :c\r",
+                initial_path.green().bold()
+            );
+            prompt_manager::ask_restore_directory(&trash_item.name, is_test)
+                .map(|directory| RestoreTarget::new(join_path(&directory, &trash_item.name)))
+        }
+    }
+}
+
+// Restore some files of trashed directories, the rest of the directories stays in the trash. The
+// files are given by globs (4/src/*.rs), or picked from the list of the files when only the id of
+// the element is given. Return false if one of them couldn't be restored.
+pub fn restore_element_files(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    element_paths: &[ElementPath],
+    restore_options: &RestoreOptions,
+    is_pruning: bool,
+) -> bool {
+    let mut trash_items_ids: Vec<i32> = Vec::new();
+    element_paths.iter().for_each(|element_path| {
+        if !trash_items_ids.contains(&element_path.id) {
+            trash_items_ids.push(element_path.id);
+        }
+    });
+
+    let mut is_success = true;
+    for trash_item_id in trash_items_ids {
+        let patterns: Vec<&str> = element_paths
+            .iter()
+            .filter(|element_path| {
+                element_path.id == trash_item_id && !element_path.inner_path.is_empty()
+            })
+            .map(|element_path| element_path.inner_path.as_str())
+            .collect();
+        is_success &= restore_files(
+            connection,
+            config,
+            is_test,
+            trash_item_id,
+            &patterns,
+            restore_options,
+            is_pruning,
+        );
+    }
+    is_success
+}
+
+fn restore_files(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    trash_item_id: i32,
+    patterns: &[&str],
+    restore_options: &RestoreOptions,
+    is_pruning: bool,
+) -> bool {
+    let mut trash_item =
+        match data_manager::find_trash_item_by_id(connection, is_test, trash_item_id) {
+            Ok(trash_item) => trash_item,
+            Err(_) => {
+                println!(
+                    "{}: there is no element with the id {} in the trash.",
+                    "Error".red().bold(),
+                    trash_item_id.to_string().red().bold()
+                );
+                return false;
+            }
+        };
+    if !trash_item.is_folder {
+        println!(
+            "{}: {} is a file, restore it with --restore.",
+            "Error".red().bold(),
+            trash_item.name.red().bold()
+        );
+        return false;
+    }
+    let read_error = |e: io::Error| {
+        println!(
+            "{}: unable to read {} ({}).",
+            "Error".red().bold(),
+            trash_item.name.red().bold(),
+            e
+        );
+    };
+    let encryption_key = match inspect_manager::get_encryption_key(&trash_item, is_test) {
+        Ok(encryption_key) => encryption_key,
+        Err(e) => {
+            read_error(e);
+            return false;
+        }
+    };
+    let entries = match StoredElement::open(&trash_item, encryption_key.as_ref(), is_test)
+        .and_then(|mut stored_element| stored_element.entries())
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            read_error(e);
+            return false;
+        }
+    };
+
+    let selected_paths = if patterns.is_empty() {
+        ask_inner_paths(&trash_item, &entries)
+    } else {
+        entries
+            .iter()
+            .filter(|entry| {
+                patterns
+                    .iter()
+                    .any(|pattern| inspect_manager::matches_glob(pattern, &entry.inner_path))
+            })
+            .map(|entry| entry.inner_path.clone())
+            .collect()
+    };
+    // the content of a selected directory is selected too
+    let selected_paths: Vec<String> = selected_paths
+        .iter()
+        .filter(|inner_path| {
+            !selected_paths.iter().any(|directory| {
+                directory != *inner_path && inspect_manager::is_inside(inner_path, directory)
+            })
+        })
+        .cloned()
+        .collect();
+    if selected_paths.is_empty() {
+        println!(
+            "Nothing to restore from {}.",
+            trash_item.name.green().bold()
+        );
+        return patterns.is_empty();
+    }
+
+    let (directory, _) = restore_options.get_restore_directory(&trash_item);
+    let root_path = join_path(&directory, &trash_item.name);
+    let selected_entries: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| {
+            selected_paths
+                .iter()
+                .any(|selected_path| inspect_manager::is_inside(&entry.inner_path, selected_path))
+        })
+        .collect();
+    let targets: Vec<(&Entry, String)> = selected_entries
+        .iter()
+        .filter_map(|entry| {
+            resolve_file_target(
+                connection,
+                config,
+                &root_path,
+                entry,
+                restore_options,
+                is_test,
+            )
+            .map(|restored_path| (*entry, restored_path))
+        })
+        .collect();
+
+    let checksums =
+        data_manager::find_checksums(connection, &trash_item.hash, is_test).unwrap_or_default();
+    let trash_lock = lock_manager::lock_trash(is_test);
+    let result = StoredElement::open(&trash_item, encryption_key.as_ref(), is_test).and_then(
+        |mut stored_element| {
+            for (entry, restored_path) in &targets {
+                write_entry(&mut stored_element, entry, restored_path)?;
+                if entry.is_dir {
+                    continue;
+                }
+                println!(
+                    "{} has been restored at {}",
+                    entry.inner_path.green().bold(),
+                    restored_path.green().bold()
+                );
+                verify_restored_file(&checksums, &entry.inner_path, restored_path);
+            }
+            Ok(())
+        },
+    );
+    if let Err(e) = result {
+        println!(
+            "{}: unable to restore the files of {} ({}).",
+            "Error".red().bold(),
+            trash_item.name.red().bold(),
+            e
+        );
+        return false;
+    }
+    if !is_pruning {
+        return true;
+    }
+
+    // the selected paths skipped because of a conflict stay in the trash
+    let pruned_paths: Vec<String> = selected_paths
+        .into_iter()
+        .filter(|selected_path| {
+            selected_entries
+                .iter()
+                .filter(|entry| inspect_manager::is_inside(&entry.inner_path, selected_path))
+                .all(|entry| targets.iter().any(|(target, _)| target == entry))
+        })
+        .collect();
+    let is_emptied = entries.iter().all(|entry| {
+        pruned_paths
+            .iter()
+            .any(|pruned_path| inspect_manager::is_inside(&entry.inner_path, pruned_path))
+    });
+    if is_emptied {
+        drop(trash_lock);
+        println!(
+            "Every file of {} has been restored.",
+            trash_item.name.green().bold()
+        );
+        trash_manager::remove_all_elements_selected(connection, is_test, &[trash_item.id]);
+        return true;
+    }
+    if pruned_paths.is_empty() {
+        return true;
+    }
+    match storage_manager::prune_element(
+        connection,
+        &mut trash_item,
+        &pruned_paths,
+        encryption_key.as_ref(),
+        is_test,
+    ) {
+        Ok(()) => {
+            println!(
+                "The restored files have been removed from {}.",
+                trash_item.name.green().bold()
+            );
+            true
+        }
+        Err(e) => {
+            println!(
+                "{}: unable to remove the restored files from {} ({}).",
+                "Error".red().bold(),
+                trash_item.name.red().bold(),
+                e
+            );
+            false
+        }
+    }
+}
+
+// Show the entries of the directory and let the user pick the ones to restore
+fn ask_inner_paths(trash_item: &TrashItem, entries: &[Entry]) -> Vec<String> {
+    entries.iter().enumerate().for_each(|(index, entry)| {
+        println!(
+            "{:>4}  {}",
+            index + 1,
+            if entry.is_dir {
+                format!("{}/", entry.inner_path).blue().bold()
+            } else {
+                entry.inner_path.normal()
+            }
+        )
+    });
+    let message = format!(
+        "Which entries of {} do you want to restore ? (for example 1 3, nothing to cancel)",
+        trash_item.name.green().bold()
+    );
+    display_manager::get_user_selection(&message, entries.len())
+        .into_iter()
+        .map(|index| entries[index].inner_path.clone())
+        .collect()
+}
+
+// Where the entry is restored, None if it has to stay in the trash. The directories are merged with
+// the existing ones, and the conflicts of the files are handled like the conflicts of the elements,
+// except that the user is never asked for another directory: both versions are kept.
+fn resolve_file_target(
+    connection: &Connection,
+    config: &Config,
+    root_path: &str,
+    entry: &Entry,
+    restore_options: &RestoreOptions,
+    is_test: bool,
+) -> Option<String> {
+    let restored_path = Path::new(root_path)
+        .join(&entry.inner_path)
+        .to_string_lossy()
+        .into_owned();
+    if entry.is_dir || !Path::new(&restored_path).exists() {
+        return Some(restored_path);
+    }
+    match restore_options.on_conflict {
+        ConflictPolicy::Skip => {
+            println!(
+                "{} is skipped, {} already exists.",
+                entry.inner_path.green().bold(),
+                restored_path.green().bold()
+            );
+            None
+        }
+        ConflictPolicy::Overwrite => {
+            let arguments_manager = ArgumentsManager {
+                is_test,
+                ..Default::default()
+            };
+            trash_manager::add_element_to_trash(
+                connection,
+                config,
+                &restored_path,
+                &arguments_manager,
+            );
+            Some(restored_path)
+        }
+        ConflictPolicy::Rename | ConflictPolicy::Merge | ConflictPolicy::Prompt => {
+            let path = Path::new(&restored_path);
+            Some(get_free_path(
+                &path.parent().unwrap_or(path).to_string_lossy(),
+                &path.file_name().unwrap_or_default().to_string_lossy(),
+                false,
+            ))
+        }
+    }
+}
+
+fn write_entry(
+    stored_element: &mut StoredElement,
+    entry: &Entry,
+    restored_path: &str,
+) -> io::Result<()> {
+    if entry.is_dir {
+        return fs::create_dir_all(restored_path);
+    }
+    if let Some(parent) = Path::new(restored_path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut reader = stored_element.open_file(&entry.inner_path)?;
+    io::copy(&mut reader, &mut File::create(restored_path)?)?;
+    Ok(())
+}
+
+// Check a restored file against the checksum computed when its directory was trashed
+fn verify_restored_file(checksums: &[(String, String)], inner_path: &str, restored_path: &str) {
+    let digest = match checksums.iter().find(|(path, _)| path == inner_path) {
+        Some((_, digest)) => digest,
+        None => return,
+    };
+    match checksum_manager::compute_element_checksums(restored_path) {
+        Ok(restored_checksums) if &restored_checksums.content_hash == digest => (),
+        _ => println!(
+            "{}: the content of {} doesn't match the checksum computed when it was trashed.",
+            "Warning".red().bold(),
+            restored_path.red().bold()
+        ),
+    }
+}
+
+// The metadata of the directories containing the element, saved when it is trashed
+#[cfg(unix)]
+pub fn get_parents_metadata(element_path: &str) -> Vec<DirectoryMetadata> {
+    use std::os::unix::fs::MetadataExt;
+
+    Path::new(element_path)
+        .ancestors()
+        .skip(1)
+        .filter(|directory| directory.parent().is_some())
+        .filter_map(|directory| {
+            let metadata = fs::metadata(directory).ok()?;
+            Some(DirectoryMetadata {
+                path: directory.to_string_lossy().into_owned(),
+                mode: metadata.mode(),
+                modified: metadata.mtime(),
+                uid: metadata.uid(),
+                gid: metadata.gid(),
+            })
+        })
+        .collect()
+}
+
+// The permissions and owners are unix ones, elsewhere the directories are recreated as they are
+#[cfg(not(unix))]
+pub fn get_parents_metadata(_element_path: &str) -> Vec<DirectoryMetadata> {
+    Vec::new()
+}
+
+// Create the directory and its missing parents, return the created directories, the highest first
+fn create_parent_directories(directory: &str) -> io::Result<Vec<String>> {
+    let mut created_directories: Vec<String> = Path::new(directory)
+        .ancestors()
+        .take_while(|ancestor| !ancestor.exists())
+        .map(|ancestor| ancestor.to_string_lossy().into_owned())
+        .collect();
+    created_directories.reverse();
+    fs::create_dir_all(directory)?;
+    Ok(created_directories)
+}
+
+// The modification time is set before the permissions, which could forbid to open the directory
+#[cfg(unix)]
+fn apply_directory_metadata(directory_metadata: &DirectoryMetadata) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // only root can give a directory to another user, the owner is kept when it fails
+    let _ = std::os::unix::fs::chown(
+        &directory_metadata.path,
+        Some(directory_metadata.uid),
+        Some(directory_metadata.gid),
+    );
+    if directory_metadata.modified >= 0 {
+        File::open(&directory_metadata.path)?
+            .set_modified(UNIX_EPOCH + Duration::from_secs(directory_metadata.modified as u64))?;
+    }
+    fs::set_permissions(
+        &directory_metadata.path,
+        fs::Permissions::from_mode(directory_metadata.mode & 0o7777),
+    )
+}
+
+#[cfg(not(unix))]
+fn apply_directory_metadata(_directory_metadata: &DirectoryMetadata) -> io::Result<()> {
+    Ok(())
+}
+
+// Once the element is restored, give the recreated directories their saved metadata. If the
+// element hasn't been restored, the recreated directories are removed.
+pub fn finish_created_directories(
+    connection: &Connection,
+    is_test: bool,
+    restore_target: &RestoreTarget,
+    is_restored: bool,
+) {
+    if !is_restored {
+        restore_target
+            .created_directories
+            .iter()
+            .rev()
+            .for_each(|directory| {
+                let _ = fs::remove_dir(directory);
+            });
+        return;
+    }
+    restore_target
+        .created_directories
+        .iter()
+        .rev()
+        .filter_map(|directory| {
+            data_manager::find_directory_metadata(connection, is_test, directory)
+        })
+        .for_each(|directory_metadata| {
+            if let Err(e) = apply_directory_metadata(&directory_metadata) {
+                println!(
+                    "{}: unable to restore the metadata of {} ({}).\r",
+                    "Warning".yellow().bold(),
+                    directory_metadata.path.green().bold(),
+                    e
+                );
+            }
+        });
+}
+
+// "report.pdf" -> "report (1).pdf", the number is before the extension of a file only
+fn get_numbered_name(name: &str, is_folder: bool, number: usize) -> String {
+    match name.rfind('.') {
+        Some(dot_index) if !is_folder && dot_index > 0 => {
+            format!("{} ({}){}", &name[..dot_index], number, &name[dot_index..])
+        }
+        _ => format!("{} ({})", name, number),
+    }
+}
+
+// The first "name (n)" which doesn't exist inside the directory
+pub fn get_free_path(directory: &str, name: &str, is_folder: bool) -> String {
+    (1..)
+        .map(|number| join_path(directory, &get_numbered_name(name, is_folder, number)))
+        .find(|path| !Path::new(path).exists())
+        .unwrap()
+}
+
+// Move the content of the source directory into the destination directory, the elements existing
+// on both sides are kept both, then remove the source directory
+fn merge_directories(source_path: &Path, destination_path: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(source_path)? {
+        let entry = entry?;
+        let entry_source_path = entry.path();
+        let entry_destination_path = destination_path.join(entry.file_name());
+        if !entry_destination_path.exists() {
+            fs::rename(&entry_source_path, &entry_destination_path)?;
+        } else if entry_source_path.is_dir() && entry_destination_path.is_dir() {
+            merge_directories(&entry_source_path, &entry_destination_path)?;
+        } else {
+            let free_path = get_free_path(
+                &destination_path.to_string_lossy(),
+                &entry.file_name().to_string_lossy(),
+                entry_source_path.is_dir(),
+            );
+            println!(
+                "{} already exists, it is kept next to {}.\r",
+                entry_destination_path.to_string_lossy().green().bold(),
+                free_path.green().bold()
+            );
+            fs::rename(&entry_source_path, &free_path)?;
+        }
+    }
+    fs::remove_dir(source_path)
+}
+
+// Merge the directory restored next to an existing one into it
+pub fn merge_restored_directory(restored_path: &str, merge_path: &str) {
+    match merge_directories(Path::new(restored_path), Path::new(merge_path)) {
+        Ok(_) => println!("It has been merged into {}\r", merge_path.green().bold()),
+        Err(e) => println!(
+            "{}: unable to merge {} into {} ({}).\r",
+            "Error".red().bold(),
+            restored_path.green().bold(),
+            merge_path.green().bold(),
+            e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_manager;
+    use crate::structure_manager::{self, get_trash_directory_path};
+
+    #[test]
+    fn test_get_free_path() {
+        let is_test = true;
+        structure_manager::setup_structure(is_test);
+        let directory = get_trash_directory_path(is_test);
+
+        fs::write(join_path(&directory, "report.pdf"), "rmt").unwrap();
+        fs::write(join_path(&directory, "report (1).pdf"), "rmt").unwrap();
+        assert_eq!(
+            get_free_path(&directory, "report.pdf", false),
+            join_path(&directory, "report (2).pdf")
+        );
+        assert_eq!(
+            get_free_path(&directory, "project.d", true),
+            join_path(&directory, "project.d (1)")
+        );
+        assert_eq!(get_numbered_name(".bashrc", false, 1), ".bashrc (1)");
+
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_restore_conflicts() {
+        let is_test = true;
+        let (config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let directory = join_path(&get_trash_directory_path(is_test), "conflicts");
+        let file_path = join_path(&directory, "a.txt");
+        let restore = |on_conflict: ConflictPolicy| {
+            let trash_item = data_manager::find_all_trash_items(&connection, is_test)
+                .into_iter()
+                .find(|trash_item| trash_item.name == "a.txt")
+                .unwrap();
+            trash_manager::restore_all_elements_selected(
+                &connection,
+                &config,
+                is_test,
+                &[trash_item.id],
+                &RestoreOptions {
+                    on_conflict,
+                    ..Default::default()
+                },
+            );
+        };
+        // trash a file with the content, and put back the existing file
+        let trash_file = |content: &str| {
+            let existing_content = fs::read_to_string(&file_path).ok();
+            fs::write(&file_path, content).unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &file_path,
+                &arguments_manager,
+            );
+            if let Some(existing_content) = existing_content {
+                fs::write(&file_path, existing_content).unwrap();
+            }
+        };
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&file_path, "existing").unwrap();
+
+        trash_file("trashed");
+        restore(ConflictPolicy::Skip);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "existing");
+
+        restore(ConflictPolicy::Rename);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "existing");
+        assert_eq!(
+            fs::read_to_string(join_path(&directory, "a (1).txt")).unwrap(),
+            "trashed"
+        );
+
+        // the existing file goes to the trash, and comes back in place of the restored one
+        trash_file("trashed");
+        restore(ConflictPolicy::Overwrite);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "trashed");
+        restore(ConflictPolicy::Overwrite);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "existing");
+
+        // merge a trashed directory into the existing one
+        let merged_directory = join_path(&directory, "merged");
+        fs::create_dir_all(join_path(&merged_directory, "sub")).unwrap();
+        fs::write(join_path(&merged_directory, "sub/b.txt"), "trashed").unwrap();
+        fs::write(join_path(&merged_directory, "c.txt"), "trashed").unwrap();
+        trash_manager::add_element_to_trash(
+            &connection,
+            &config,
+            &merged_directory,
+            &arguments_manager,
+        );
+        fs::create_dir_all(&merged_directory).unwrap();
+        fs::write(join_path(&merged_directory, "c.txt"), "existing").unwrap();
+        let trash_item = data_manager::find_all_trash_items(&connection, is_test)
+            .into_iter()
+            .find(|trash_item| trash_item.name == "merged")
+            .unwrap();
+        trash_manager::restore_all_elements_selected(
+            &connection,
+            &config,
+            is_test,
+            &[trash_item.id],
+            &RestoreOptions {
+                on_conflict: ConflictPolicy::Merge,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&merged_directory, "sub/b.txt")).unwrap(),
+            "trashed"
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&merged_directory, "c.txt")).unwrap(),
+            "existing"
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&merged_directory, "c (1).txt")).unwrap(),
+            "trashed"
+        );
+        assert!(!Path::new(&join_path(&directory, "merged (1)")).exists());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recreate_parents() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let is_test = true;
+        let (config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let project = join_path(&get_trash_directory_path(is_test), "project");
+        let directory = join_path(&project, "src");
+        let file_path = join_path(&directory, "main.rs");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&file_path, "fn main() {}").unwrap();
+        fs::set_permissions(&directory, fs::Permissions::from_mode(0o750)).unwrap();
+        File::open(&directory)
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+            .unwrap();
+
+        trash_manager::add_element_to_trash(&connection, &config, &file_path, &arguments_manager);
+        fs::remove_dir_all(&project).unwrap();
+        let trash_item = data_manager::find_all_trash_items(&connection, is_test)
+            .into_iter()
+            .find(|trash_item| trash_item.name == "main.rs")
+            .unwrap();
+        trash_manager::restore_all_elements_selected(
+            &connection,
+            &config,
+            is_test,
+            &[trash_item.id],
+            &RestoreOptions {
+                on_conflict: ConflictPolicy::Skip,
+                is_recreating_parents: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "fn main() {}");
+        let metadata = fs::metadata(&directory).unwrap();
+        assert_eq!(metadata.mode() & 0o7777, 0o750);
+        assert_eq!(metadata.mtime(), 1_000_000_000);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_path_map() {
+        let path_map = PathMap::parse("/home/alice=/home/al/").unwrap();
+        assert_eq!(
+            path_map.apply("/home/alice/work"),
+            Some(("/home/al/work".to_string(), "/home/alice".len()))
+        );
+        assert_eq!(
+            path_map.apply("/home/alice"),
+            Some(("/home/al".to_string(), "/home/alice".len()))
+        );
+        assert_eq!(path_map.apply("/home/alice2/work"), None);
+        assert!(PathMap::parse("/home/alice").is_err());
+        assert!(PathMap::parse("=/home/al").is_err());
+
+        let restore_options = RestoreOptions {
+            path_maps: vec![
+                PathMap::parse("/home=/mnt/home").unwrap(),
+                PathMap::parse("/home/alice=/home/al").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let trash_item = TrashItem::new(
+            "a.txt".to_string(),
+            "hash".to_string(),
+            "/home/alice/work".to_string(),
+            0,
+            0,
+            None,
+            false,
+            false,
+            false,
+        );
+        assert_eq!(
+            restore_options.get_restore_directory(&trash_item),
+            ("/home/al/work".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_restore_to_destination() {
+        let is_test = true;
+        let (config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let trash_directory = get_trash_directory_path(is_test);
+        let directory = join_path(&trash_directory, "old_clone");
+        fs::create_dir_all(join_path(&directory, "src")).unwrap();
+        for name in ["a.txt", "src/b.txt"] {
+            fs::write(join_path(&directory, name), name).unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &join_path(&directory, name),
+                &arguments_manager,
+            );
+        }
+        let ids: Vec<i32> = data_manager::find_all_trash_items(&connection, is_test)
+            .iter()
+            .map(|trash_item| trash_item.id)
+            .collect();
+
+        // the prompt policy renames the element instead of asking for another directory
+        let new_clone = join_path(&trash_directory, "new_clone");
+        fs::create_dir_all(&new_clone).unwrap();
+        fs::write(join_path(&new_clone, "a.txt"), "existing").unwrap();
+        trash_manager::restore_all_elements_selected(
+            &connection,
+            &config,
+            is_test,
+            &ids,
+            &RestoreOptions {
+                path_maps: vec![PathMap::parse(&format!("{}={}", directory, new_clone)).unwrap()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&new_clone, "a (1).txt")).unwrap(),
+            "a.txt"
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&new_clone, "src/b.txt")).unwrap(),
+            "src/b.txt"
+        );
+
+        fs::write(join_path(&new_clone, "a (1).txt"), "a.txt").unwrap();
+        trash_manager::add_element_to_trash(
+            &connection,
+            &config,
+            &join_path(&new_clone, "a (1).txt"),
+            &arguments_manager,
+        );
+        let ids: Vec<i32> = data_manager::find_all_trash_items(&connection, is_test)
+            .iter()
+            .map(|trash_item| trash_item.id)
+            .collect();
+        let destination = join_path(&trash_directory, "recovered");
+        trash_manager::restore_all_elements_selected(
+            &connection,
+            &config,
+            is_test,
+            &ids,
+            &RestoreOptions {
+                destination: Some(destination.clone()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            fs::read_to_string(join_path(&destination, "a (1).txt")).unwrap(),
+            "a.txt"
+        );
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_restore_element_files() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let directory = join_path(&get_trash_directory_path(is_test), "partial");
+        let element_path = |id: i32, inner_path: &str| ElementPath {
+            id,
+            inner_path: inner_path.to_string(),
+        };
+
+        for compression in [false, true] {
+            config.compression = compression;
+            fs::create_dir_all(join_path(&directory, "src/bin")).unwrap();
+            fs::write(join_path(&directory, "src/main.rs"), "fn main() {}").unwrap();
+            fs::write(join_path(&directory, "src/bin/tool.rs"), "tool").unwrap();
+            fs::write(join_path(&directory, "README.md"), "# rmt").unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &directory,
+                &arguments_manager,
+            );
+            let trash_item = data_manager::find_all_trash_items(&connection, is_test)
+                .pop()
+                .unwrap();
+
+            assert!(restore_element_files(
+                &connection,
+                &config,
+                is_test,
+                &[element_path(trash_item.id, "src/**/*.rs")],
+                &RestoreOptions::default(),
+                false,
+            ));
+            assert_eq!(
+                fs::read_to_string(join_path(&directory, "src/bin/tool.rs")).unwrap(),
+                "tool"
+            );
+            assert!(!Path::new(&join_path(&directory, "README.md")).exists());
+
+            // the restored directory is removed from the stored one
+            fs::remove_dir_all(&directory).unwrap();
+            assert!(restore_element_files(
+                &connection,
+                &config,
+                is_test,
+                &[element_path(trash_item.id, "src")],
+                &RestoreOptions::default(),
+                true,
+            ));
+            assert_eq!(
+                fs::read_to_string(join_path(&directory, "src/main.rs")).unwrap(),
+                "fn main() {}"
+            );
+            let pruned_item =
+                data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).unwrap();
+            assert_eq!(pruned_item.real_size, 5);
+            let mut stored_element = StoredElement::open(&pruned_item, None, is_test).unwrap();
+            assert_eq!(stored_element.entries().unwrap().len(), 1);
+            assert!(checksum_manager::verify_elements(
+                &connection,
+                is_test,
+                &[trash_item.id]
+            ));
+
+            // once empty, the element is removed from the trash
+            assert!(restore_element_files(
+                &connection,
+                &config,
+                is_test,
+                &[element_path(trash_item.id, "*.md")],
+                &RestoreOptions::default(),
+                true,
+            ));
+            assert!(
+                data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).is_err()
+            );
+            assert_eq!(
+                fs::read_to_string(join_path(&directory, "README.md")).unwrap(),
+                "# rmt"
+            );
+            fs::remove_dir_all(&directory).unwrap();
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
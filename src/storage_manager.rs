@@ -0,0 +1,705 @@
+// An element is stored in the trash directory as it is, compressed (zip), encrypted, or compressed
+// then encrypted. It can be converted from one storage format to another in place: rmt --gc
+// compresses the old elements (cold_storage in the configuration) and rmt --convert applies the
+// compression and encryption of the configuration to the elements already in the trash. Files can
+// also be removed from a stored directory the same way, once restored on their own.
+//
+// The element is decoded and encoded again next to itself, then it replaces the old version. The
+// conversion is saved in the journal before it starts, so an interrupted one is rolled back or
+// finished at the next start (see journal_manager).
+
+use crate::checksum_manager::compute_element_checksums;
+use crate::config::{ColdStorage, Config};
+use crate::data_manager::{self, TrashItemsOrder};
+use crate::display_manager::{format_size, SizeUnits};
+use crate::encryption_manager::{decrypt_element, encrypt_element, EncryptionKey};
+use crate::journal_manager::{self, remove_path, JournalEntry, Operation};
+use crate::lock_manager;
+use crate::query_manager::{Comparison, Condition, Query};
+use crate::structure_manager::get_path_in_trash;
+use crate::trash_item::TrashItem;
+use crate::trash_manager::{compress_element, decompress_element};
+
+use colored::Colorize;
+use fs_extra::dir::{self, get_size};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, MAIN_SEPARATOR};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageFormat {
+    pub is_compressed: bool,
+    pub is_encrypted: bool,
+}
+
+impl StorageFormat {
+    // a directory can only be encrypted once compressed
+    pub fn new(is_folder: bool, is_compressed: bool, is_encrypted: bool) -> Self {
+        Self {
+            is_compressed,
+            is_encrypted: is_encrypted && (is_compressed || !is_folder),
+        }
+    }
+
+    pub fn of(trash_item: &TrashItem) -> Self {
+        Self {
+            is_compressed: trash_item.is_compressed,
+            is_encrypted: trash_item.is_encrypted,
+        }
+    }
+}
+
+pub fn get_convert_directory_path(path_in_trash: &str) -> String {
+    format!("{}.convert", path_in_trash)
+}
+
+pub fn get_converted_path(path_in_trash: &str) -> String {
+    format!("{}.converted", path_in_trash)
+}
+
+// The old element waits there until the database is updated
+pub fn get_replaced_path(path_in_trash: &str) -> String {
+    format!("{}.replaced", path_in_trash)
+}
+
+fn missing_key_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "the encryption key is needed")
+}
+
+// Convert the element to the storage format and save it in the database. The key is needed if
+// the element is encrypted before or after the conversion.
+pub fn convert_element(
+    connection: &Connection,
+    trash_item: &mut TrashItem,
+    storage_format: StorageFormat,
+    encryption_key: Option<&EncryptionKey>,
+    is_test: bool,
+) -> io::Result<()> {
+    if StorageFormat::of(trash_item) == storage_format {
+        return Ok(());
+    }
+    let (journal_id, mut journal_entry) = start_rewrite(
+        connection,
+        trash_item,
+        Operation::Convert(storage_format),
+        is_test,
+    );
+    match rewrite_element(trash_item, storage_format, encryption_key, is_test, None) {
+        Ok(compression_size) => journal_entry.trash_item.compression_size = compression_size,
+        Err(e) => {
+            data_manager::delete_journal_entry(connection, journal_id, is_test);
+            return Err(e);
+        }
+    }
+    journal_entry.trash_item.is_compressed = storage_format.is_compressed;
+    journal_entry.trash_item.is_encrypted = storage_format.is_encrypted;
+    journal_manager::mark_copied(connection, journal_id, &mut journal_entry, is_test);
+    finish_rewrite(connection, journal_id, &journal_entry, is_test)?;
+    *trash_item = journal_entry.trash_item;
+    Ok(())
+}
+
+// Save the intent in the journal before the element is rewritten at the converted path, return
+// the id of the journal entry and the entry, to be completed with the rewritten trash item
+fn start_rewrite(
+    connection: &Connection,
+    trash_item: &TrashItem,
+    operation: Operation,
+    is_test: bool,
+) -> (i64, JournalEntry) {
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+    let mut journal_entry = JournalEntry::new(
+        operation,
+        trash_item.clone(),
+        get_converted_path(&path_in_trash),
+        path_in_trash.clone(),
+    );
+    journal_entry.temporary_path = Some(get_convert_directory_path(&path_in_trash));
+    let journal_id = journal_manager::start(connection, &journal_entry, is_test);
+    (journal_id, journal_entry)
+}
+
+// The rewritten element is complete: it replaces the element, the database is updated and the old
+// element is removed. It is also used to finish an interrupted conversion or pruning.
+pub fn finish_rewrite(
+    connection: &Connection,
+    journal_id: i64,
+    journal_entry: &JournalEntry,
+    is_test: bool,
+) -> io::Result<()> {
+    replace_element(&journal_entry.destination_path)?;
+    journal_manager::finish(connection, journal_id, journal_entry, is_test);
+    remove_path(&get_replaced_path(&journal_entry.destination_path));
+    Ok(())
+}
+
+// Remove files and directories from a stored directory, it keeps its storage format. Its size and
+// checksums are computed again and saved in the database.
+pub fn prune_element(
+    connection: &Connection,
+    trash_item: &mut TrashItem,
+    inner_paths: &[String],
+    encryption_key: Option<&EncryptionKey>,
+    is_test: bool,
+) -> io::Result<()> {
+    let mut pruned_content = None;
+    let mut prune = |plain_path: &str| -> io::Result<()> {
+        for inner_path in inner_paths {
+            remove_path(&Path::new(plain_path).join(inner_path).to_string_lossy());
+        }
+        let real_size = get_size(plain_path).map_err(|e| io::Error::other(e.to_string()))?;
+        pruned_content = Some((real_size, compute_element_checksums(plain_path)?));
+        Ok(())
+    };
+    let (journal_id, mut journal_entry) =
+        start_rewrite(connection, trash_item, Operation::Prune, is_test);
+    match rewrite_element(
+        trash_item,
+        StorageFormat::of(trash_item),
+        encryption_key,
+        is_test,
+        Some(&mut prune),
+    ) {
+        Ok(compression_size) => journal_entry.trash_item.compression_size = compression_size,
+        Err(e) => {
+            data_manager::delete_journal_entry(connection, journal_id, is_test);
+            return Err(e);
+        }
+    }
+    let (real_size, checksums) = pruned_content.unwrap();
+    journal_entry.trash_item.real_size = real_size;
+    journal_entry.trash_item.content_hash = Some(checksums.content_hash);
+    journal_entry.checksums = checksums.files;
+    journal_manager::mark_copied(connection, journal_id, &mut journal_entry, is_test);
+    finish_rewrite(connection, journal_id, &journal_entry, is_test)?;
+    *trash_item = journal_entry.trash_item;
+    Ok(())
+}
+
+// Changes the plain element at the given path
+type EditFunction<'a> = dyn FnMut(&str) -> io::Result<()> + 'a;
+
+// Decode the element, change it with the edit function and encode it in the storage format at the
+// converted path, return the size of the archive
+fn rewrite_element(
+    trash_item: &TrashItem,
+    storage_format: StorageFormat,
+    encryption_key: Option<&EncryptionKey>,
+    is_test: bool,
+    edit: Option<&mut EditFunction>,
+) -> io::Result<Option<u64>> {
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+    let convert_directory = get_convert_directory_path(&path_in_trash);
+    let converted_path = get_converted_path(&path_in_trash);
+    // left by an interrupted conversion
+    remove_path(&convert_directory);
+    remove_path(&converted_path);
+    remove_path(&get_replaced_path(&path_in_trash));
+
+    fs::create_dir(&convert_directory)?;
+    let result = decode_element(
+        trash_item,
+        &path_in_trash,
+        &convert_directory,
+        encryption_key,
+    )
+    .and_then(|plain_path| match edit {
+        // the element stored as it is must stay untouched until the swap
+        Some(edit) if plain_path == path_in_trash => {
+            let copied_path = format!("{}{}plain", convert_directory, MAIN_SEPARATOR);
+            copy_element(&path_in_trash, &copied_path)?;
+            edit(&copied_path)?;
+            Ok(copied_path)
+        }
+        Some(edit) => edit(&plain_path).map(|_| plain_path),
+        None => Ok(plain_path),
+    })
+    .and_then(|plain_path| {
+        encode_element(
+            trash_item,
+            &plain_path,
+            &convert_directory,
+            &converted_path,
+            storage_format,
+            encryption_key,
+        )
+    });
+    remove_path(&convert_directory);
+    if result.is_err() {
+        remove_path(&converted_path);
+    }
+    result
+}
+
+fn copy_element(source_path: &str, dist_path: &str) -> io::Result<()> {
+    if Path::new(source_path).is_dir() {
+        fs::create_dir_all(dist_path)?;
+        let mut copy_options = dir::CopyOptions::new();
+        copy_options.content_only = true;
+        dir::copy(source_path, dist_path, &copy_options)
+            .map(|_| ())
+            .map_err(|e| io::Error::other(e.to_string()))
+    } else {
+        fs::copy(source_path, dist_path).map(|_| ())
+    }
+}
+
+// Write the plain element inside the convert directory, return its path
+fn decode_element(
+    trash_item: &TrashItem,
+    path_in_trash: &str,
+    convert_directory: &str,
+    encryption_key: Option<&EncryptionKey>,
+) -> io::Result<String> {
+    let plain_directory = format!("{}{}plain", convert_directory, MAIN_SEPARATOR);
+    fs::create_dir(&plain_directory)?;
+    let plain_path = format!("{}{}{}", plain_directory, MAIN_SEPARATOR, trash_item.name);
+
+    match (trash_item.is_encrypted, trash_item.is_compressed) {
+        (true, true) => {
+            let decrypted_path = format!("{}{}decrypted.zip", convert_directory, MAIN_SEPARATOR);
+            decrypt_element(
+                path_in_trash,
+                &decrypted_path,
+                encryption_key.ok_or_else(missing_key_error)?,
+            )?;
+            decompress_element(&decrypted_path, &plain_path)?;
+        }
+        (true, false) => decrypt_element(
+            path_in_trash,
+            &plain_path,
+            encryption_key.ok_or_else(missing_key_error)?,
+        )?,
+        (false, true) => decompress_element(path_in_trash, &plain_path)?,
+        // the element is read where it is
+        (false, false) => return Ok(path_in_trash.to_string()),
+    }
+    Ok(plain_path)
+}
+
+// Write the element in the storage format at the converted path, return the size of the archive
+fn encode_element(
+    trash_item: &TrashItem,
+    plain_path: &str,
+    convert_directory: &str,
+    converted_path: &str,
+    storage_format: StorageFormat,
+    encryption_key: Option<&EncryptionKey>,
+) -> io::Result<Option<u64>> {
+    if storage_format.is_compressed {
+        let compressed_path = format!("{}{}encoded.zip", convert_directory, MAIN_SEPARATOR);
+        compress_element(plain_path, &trash_item.name, &compressed_path)?;
+        let compression_size = fs::metadata(&compressed_path)?.len();
+        if storage_format.is_encrypted {
+            encrypt_element(
+                &compressed_path,
+                converted_path,
+                encryption_key.ok_or_else(missing_key_error)?,
+            )?;
+        } else {
+            fs::rename(&compressed_path, converted_path)?;
+        }
+        Ok(Some(compression_size))
+    } else {
+        if storage_format.is_encrypted {
+            encrypt_element(
+                plain_path,
+                converted_path,
+                encryption_key.ok_or_else(missing_key_error)?,
+            )?;
+        } else {
+            fs::rename(plain_path, converted_path)?;
+        }
+        Ok(None)
+    }
+}
+
+// Put the converted element in place of the element, the old element is moved to the replaced
+// path first. Each step can be found back from the paths left, so it can be run again to finish an
+// interrupted replacement.
+fn replace_element(path_in_trash: &str) -> io::Result<()> {
+    let converted_path = get_converted_path(path_in_trash);
+    if !Path::new(&converted_path).exists() {
+        return Ok(());
+    }
+    if Path::new(path_in_trash).exists() {
+        fs::rename(path_in_trash, get_replaced_path(path_in_trash))?;
+    }
+    fs::rename(&converted_path, path_in_trash)
+}
+
+// Convert the elements one by one, return false if one of them can't be converted
+pub fn convert_elements(
+    connection: &Connection,
+    conversions: Vec<(TrashItem, StorageFormat)>,
+    is_test: bool,
+    size_units: SizeUnits,
+) -> bool {
+    if conversions.is_empty() {
+        println!("There is no element to convert.");
+        return true;
+    }
+
+    let is_key_needed = conversions
+        .iter()
+        .any(|(trash_item, storage_format)| trash_item.is_encrypted || storage_format.is_encrypted);
+    let encryption_key = if is_key_needed {
+        // the key is new if no element was encrypted so far
+        let with_confirmation = !conversions
+            .iter()
+            .any(|(trash_item, _)| trash_item.is_encrypted);
+        Some(EncryptionKey::get(is_test, with_confirmation).expect("Failed to get encryption key"))
+    } else {
+        None
+    };
+    let _trash_lock = lock_manager::lock_trash(is_test);
+
+    let conversion_count = conversions.len();
+    let mut is_success = true;
+    for (index, (mut trash_item, storage_format)) in conversions.into_iter().enumerate() {
+        let progress = format!("[{}/{}]", index + 1, conversion_count);
+        // the element may have been removed by another rmt process in the meantime
+        if data_manager::find_trash_item_by_id(connection, is_test, trash_item.id).is_err() {
+            println!(
+                "{} {} is no longer in the trash.",
+                progress,
+                trash_item.name.green().bold()
+            );
+            continue;
+        }
+
+        let previous_size = trash_item.get_stored_size();
+        match convert_element(
+            connection,
+            &mut trash_item,
+            storage_format,
+            encryption_key.as_ref(),
+            is_test,
+        ) {
+            Ok(_) => println!(
+                "{} {} has been converted ({} ➜ {}).",
+                progress,
+                trash_item.name.green().bold(),
+                format_size(previous_size, size_units),
+                format_size(trash_item.get_stored_size(), size_units)
+                    .green()
+                    .bold()
+            ),
+            Err(e) => {
+                is_success = false;
+                println!(
+                    "{} {}: unable to convert {} ({}).",
+                    progress,
+                    "Error".red().bold(),
+                    trash_item.name.green().bold(),
+                    e
+                );
+            }
+        }
+    }
+    is_success
+}
+
+// Store the selected elements (or all the elements) with the compression and the encryption of
+// the configuration
+pub fn convert_to_config(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    trash_items_ids: &[i32],
+    size_units: SizeUnits,
+) -> bool {
+    let conversions: Vec<(TrashItem, StorageFormat)> =
+        data_manager::find_selected_trash_items(connection, is_test, trash_items_ids)
+            .into_iter()
+            .map(|trash_item| {
+                let storage_format =
+                    StorageFormat::new(trash_item.is_folder, config.compression, config.encryption);
+                (trash_item, storage_format)
+            })
+            .filter(|(trash_item, storage_format)| {
+                let is_converted = StorageFormat::of(trash_item) != *storage_format;
+                if !is_converted && !trash_items_ids.is_empty() {
+                    println!(
+                        "{} already matches the configuration.",
+                        trash_item.name.green().bold()
+                    );
+                }
+                is_converted
+            })
+            .collect();
+    convert_elements(connection, conversions, is_test, size_units)
+}
+
+// Compress (and encrypt) the elements older than the cold storage delay of the configuration
+pub fn collect_garbage(
+    connection: &Connection,
+    cold_storage: Option<ColdStorage>,
+    is_test: bool,
+    size_units: SizeUnits,
+) -> bool {
+    let cold_storage = match cold_storage {
+        Some(cold_storage) => cold_storage,
+        None => {
+            println!(
+                "{}: set {} in the configuration file to choose when the elements are compressed.",
+                "Error".red().bold(),
+                "cold_storage".green().bold()
+            );
+            return false;
+        }
+    };
+
+    let query = Query {
+        words: Vec::new(),
+        conditions: vec![Condition::Age(
+            Comparison::GreaterOrEqual,
+            cold_storage.after_days as u64 * 86400,
+        )],
+    };
+    let conversions: Vec<(TrashItem, StorageFormat)> = data_manager::find_queried_trash_items(
+        connection,
+        is_test,
+        &query,
+        TrashItemsOrder::Oldest,
+    )
+    .expect("Failed to get the elements of the trash")
+    .into_iter()
+    .map(|trash_item| {
+        // an element is never decrypted by the garbage collection
+        let storage_format = StorageFormat::new(
+            trash_item.is_folder,
+            true,
+            cold_storage.encryption || trash_item.is_encrypted,
+        );
+        (trash_item, storage_format)
+    })
+    .filter(|(trash_item, storage_format)| StorageFormat::of(trash_item) != *storage_format)
+    .collect();
+    convert_elements(connection, conversions, is_test, size_units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum_manager;
+    use crate::structure_manager;
+
+    fn insert_plain_element(connection: &Connection, name: &str, is_folder: bool) -> TrashItem {
+        let is_test = true;
+        let hash = sha256::digest(format!("convert_{}", name));
+        let path_in_trash = get_path_in_trash(&hash, is_test);
+        if is_folder {
+            fs::create_dir_all(format!("{}{}inner", path_in_trash, MAIN_SEPARATOR)).unwrap();
+            fs::write(
+                format!("{0}{1}inner{1}file.txt", path_in_trash, MAIN_SEPARATOR),
+                "inner content",
+            )
+            .unwrap();
+        } else {
+            fs::write(&path_in_trash, "file content ".repeat(100)).unwrap();
+        }
+        let mut trash_item = TrashItem::new(
+            name.to_string(),
+            hash,
+            "/home/user".to_string(),
+            0,
+            fs_extra::dir::get_size(&path_in_trash).unwrap(),
+            None,
+            is_folder,
+            false,
+            false,
+        );
+        trash_item.content_hash = Some(
+            checksum_manager::compute_element_checksums(&path_in_trash)
+                .unwrap()
+                .content_hash,
+        );
+        data_manager::insert_trash_item(connection, &trash_item, is_test);
+        data_manager::find_all_trash_items(connection, is_test)
+            .into_iter()
+            .find(|inserted_item| inserted_item.name == name)
+            .unwrap()
+    }
+
+    fn assert_content_unchanged(trash_item: &TrashItem) {
+        let checksums = checksum_manager::compute_stored_checksums(trash_item, true).unwrap();
+        assert_eq!(Some(checksums.content_hash), trash_item.content_hash);
+    }
+
+    #[test]
+    fn test_convert_element() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+        let encryption_key = EncryptionKey::new("convert".to_string());
+
+        for (name, is_folder) in [("convert.txt", false), ("convert_directory", true)] {
+            let mut trash_item = insert_plain_element(&connection, name, is_folder);
+            let formats = [
+                StorageFormat::new(is_folder, true, false),
+                StorageFormat::new(is_folder, true, true),
+                StorageFormat::new(is_folder, false, true),
+                StorageFormat::new(is_folder, false, false),
+            ];
+            for storage_format in formats {
+                convert_element(
+                    &connection,
+                    &mut trash_item,
+                    storage_format,
+                    Some(&encryption_key),
+                    is_test,
+                )
+                .unwrap();
+                let saved_item =
+                    data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id)
+                        .unwrap();
+                assert_eq!(saved_item, trash_item);
+                assert_eq!(StorageFormat::of(&saved_item), storage_format);
+                assert_eq!(
+                    saved_item.compression_size.is_some(),
+                    storage_format.is_compressed
+                );
+                // the checksum of an encrypted element would need the key from a prompt
+                if !storage_format.is_encrypted {
+                    assert_content_unchanged(&saved_item);
+                }
+            }
+            let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+            assert_eq!(Path::new(&path_in_trash).is_dir(), is_folder);
+            assert!(!Path::new(&get_convert_directory_path(&path_in_trash)).exists());
+            assert!(!Path::new(&get_converted_path(&path_in_trash)).exists());
+            assert!(!Path::new(&get_replaced_path(&path_in_trash)).exists());
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_recover_interrupted_conversion() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+        let storage_format = StorageFormat::new(false, true, false);
+
+        for is_replacing in [false, true] {
+            let trash_item = insert_plain_element(
+                &connection,
+                &format!("recover_conversion_{}.txt", is_replacing),
+                false,
+            );
+            let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+            let mut journal_entry = JournalEntry::new(
+                Operation::Convert(storage_format),
+                trash_item.clone(),
+                get_converted_path(&path_in_trash),
+                path_in_trash.clone(),
+            );
+            let journal_id = journal_manager::start(&connection, &journal_entry, is_test);
+            journal_entry.trash_item.compression_size =
+                rewrite_element(&trash_item, storage_format, None, is_test, None).unwrap();
+            journal_entry.trash_item.is_compressed = true;
+            if is_replacing {
+                // interrupted once the old element has been moved aside
+                journal_manager::mark_copied(&connection, journal_id, &mut journal_entry, is_test);
+                fs::rename(&path_in_trash, get_replaced_path(&path_in_trash)).unwrap();
+            }
+
+            journal_manager::recover(&connection, is_test);
+            let saved_item =
+                data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).unwrap();
+            assert_eq!(saved_item.is_compressed, is_replacing);
+            assert_content_unchanged(&saved_item);
+            assert!(!Path::new(&get_converted_path(&path_in_trash)).exists());
+            assert!(!Path::new(&get_replaced_path(&path_in_trash)).exists());
+            assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_recover_interrupted_pruning() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+
+        let trash_item = insert_plain_element(&connection, "recover_pruning", true);
+        let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+        let (journal_id, mut journal_entry) =
+            start_rewrite(&connection, &trash_item, Operation::Prune, is_test);
+        let mut prune = |plain_path: &str| -> io::Result<()> {
+            fs::remove_file(format!(
+                "{0}{1}inner{1}file.txt",
+                plain_path, MAIN_SEPARATOR
+            ))?;
+            let checksums = compute_element_checksums(plain_path)?;
+            journal_entry.trash_item.real_size = get_size(plain_path).unwrap();
+            journal_entry.trash_item.content_hash = Some(checksums.content_hash);
+            journal_entry.checksums = checksums.files;
+            Ok(())
+        };
+        rewrite_element(
+            &trash_item,
+            StorageFormat::of(&trash_item),
+            None,
+            is_test,
+            Some(&mut prune),
+        )
+        .unwrap();
+        // interrupted once the old element has been moved aside
+        journal_manager::mark_copied(&connection, journal_id, &mut journal_entry, is_test);
+        fs::rename(&path_in_trash, get_replaced_path(&path_in_trash)).unwrap();
+
+        journal_manager::recover(&connection, is_test);
+        let saved_item =
+            data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).unwrap();
+        assert!(saved_item.real_size < trash_item.real_size);
+        assert_content_unchanged(&saved_item);
+        assert!(Path::new(&format!("{}{}inner", path_in_trash, MAIN_SEPARATOR)).is_dir());
+        assert!(!Path::new(&format!(
+            "{0}{1}inner{1}file.txt",
+            path_in_trash, MAIN_SEPARATOR
+        ))
+        .exists());
+        assert!(!Path::new(&get_replaced_path(&path_in_trash)).exists());
+        assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_convert_to_config() {
+        let is_test = true;
+        let (_, connection) = structure_manager::setup_structure(is_test);
+        let mut config = Config::new_default_config();
+        config.compression = true;
+
+        let trash_item = insert_plain_element(&connection, "convert_to_config.txt", false);
+        assert!(convert_to_config(
+            &connection,
+            &config,
+            is_test,
+            &[],
+            SizeUnits::default()
+        ));
+        let converted_item =
+            data_manager::find_trash_item_by_id(&connection, is_test, trash_item.id).unwrap();
+        assert!(converted_item.is_compressed);
+        assert!(converted_item.compression_size.unwrap() < converted_item.real_size);
+        assert_content_unchanged(&converted_item);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
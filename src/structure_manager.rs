@@ -1,10 +1,14 @@
 use rusqlite::Connection;
 
-use crate::{argument_errors::RmtArgumentErrors, config::Config, config_manager, data_manager};
+use crate::{
+    argument_errors::RmtArgumentErrors, config::Config, config_manager, data_manager,
+    journal_manager, lock_manager,
+};
 use std::{
     ffi::OsStr,
     fs,
     path::{Path, MAIN_SEPARATOR},
+    time::Duration,
 };
 
 // TRASH DIRECTORY CONSTANT
@@ -19,25 +23,49 @@ const TEST_CONFIG: &str = "test_config_rmt.yml";
 const DATA_BASE_FILE_NAME: &str = "trash.db";
 const TEST_DATA_BASE_FILE_NAME: &str = "test_trash.db";
 
+//LOCK FILE CONSTANTE
+const LOCK_FILE_NAME: &str = "trash.lock";
+const TEST_LOCK_FILE_NAME: &str = "test_trash.lock";
+
+// How long a connection waits for another rmt process to release the database
+const DATA_BASE_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+//AGENT SOCKET CONSTANTE
+const AGENT_SOCKET_NAME: &str = "agent.sock";
+const TEST_AGENT_SOCKET_NAME: &str = "test_agent.sock";
+
+//RESTORE HISTORY CONSTANTE
+const RESTORE_HISTORY_FILE_NAME: &str = "restore_history";
+const TEST_RESTORE_HISTORY_FILE_NAME: &str = "test_restore_history";
+
 //DATABASE TABLE CONSTANTE
 const DATA_BASE_TABLE_NAME: &str = "trash_table";
 const TEST_DATA_BASE_TABLE_NAME: &str = "test_trash_table";
+const CHECKSUM_TABLE_NAME: &str = "checksum_table";
+const TEST_CHECKSUM_TABLE_NAME: &str = "test_checksum_table";
+const SEARCH_TABLE_NAME: &str = "search_table";
+const TEST_SEARCH_TABLE_NAME: &str = "test_search_table";
+const JOURNAL_TABLE_NAME: &str = "journal_table";
+const TEST_JOURNAL_TABLE_NAME: &str = "test_journal_table";
+const DIRECTORY_TABLE_NAME: &str = "directory_table";
+const TEST_DIRECTORY_TABLE_NAME: &str = "test_directory_table";
 
 // Setup tash directory and config file inside it and return the current config
+// Operations interrupted during a previous run are rolled back or finished, under the lock of the
+// trash so the operations in progress in another rmt process are left alone
 pub fn setup_structure(is_test: bool) -> (Config, Connection) {
     create_trash_directory(is_test);
-    (
-        create_config_file(is_test),
-        data_manager::setup_data_base(is_test),
-    )
+    let config = create_config_file(is_test);
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    let connection = data_manager::setup_data_base(is_test);
+    journal_manager::recover(&connection, is_test);
+    (config, connection)
 }
 
-// Create trash directory at the home if not exist
+// Create trash directory at the home if not exist, another rmt process may create it at the same time
 fn create_trash_directory(is_test: bool) {
     let trash_path = get_trash_directory_path(is_test);
-    if !Path::new(&trash_path).is_dir() {
-        fs::create_dir(&trash_path).expect("Unable to create trash directory");
-    }
+    fs::create_dir_all(&trash_path).expect("Unable to create trash directory");
 }
 
 // Delete trash directory, config file and database file if exists
@@ -75,6 +103,16 @@ pub fn get_trash_directory_path(is_test: bool) -> String {
     )
 }
 
+// Path of a trashed element inside the trash directory, elements are stored under their hash
+pub fn get_path_in_trash(hash: &str, is_test: bool) -> String {
+    format!(
+        "{}{}{}",
+        get_trash_directory_path(is_test),
+        MAIN_SEPARATOR,
+        hash
+    )
+}
+
 pub fn get_config_path(is_test: bool) -> String {
     let config_name = if is_test { TEST_CONFIG } else { CONFIG };
     format!(
@@ -101,6 +139,38 @@ pub fn get_data_base_table_name(is_test: bool) -> String {
     }
 }
 
+pub fn get_checksum_table_name(is_test: bool) -> String {
+    if is_test {
+        TEST_CHECKSUM_TABLE_NAME.to_string()
+    } else {
+        CHECKSUM_TABLE_NAME.to_string()
+    }
+}
+
+pub fn get_search_table_name(is_test: bool) -> String {
+    if is_test {
+        TEST_SEARCH_TABLE_NAME.to_string()
+    } else {
+        SEARCH_TABLE_NAME.to_string()
+    }
+}
+
+pub fn get_journal_table_name(is_test: bool) -> String {
+    if is_test {
+        TEST_JOURNAL_TABLE_NAME.to_string()
+    } else {
+        JOURNAL_TABLE_NAME.to_string()
+    }
+}
+
+pub fn get_directory_table_name(is_test: bool) -> String {
+    if is_test {
+        TEST_DIRECTORY_TABLE_NAME.to_string()
+    } else {
+        DIRECTORY_TABLE_NAME.to_string()
+    }
+}
+
 fn get_data_base_file_name(is_test: bool) -> String {
     if is_test {
         TEST_DATA_BASE_FILE_NAME.to_string()
@@ -109,10 +179,48 @@ fn get_data_base_file_name(is_test: bool) -> String {
     }
 }
 
+// The database is shared by all the rmt processes: in WAL mode the readers don't block the writer,
+// and a connection waits for the database to be free instead of failing right away
 pub fn create_data_base_file(is_test: bool) -> Connection {
     let data_base_path = get_data_base_path(is_test);
-    Connection::open(&data_base_path)
-        .unwrap_or_else(|_| panic!("Unable to create {} file", &data_base_path))
+    let connection = Connection::open(&data_base_path)
+        .unwrap_or_else(|_| panic!("Unable to create {} file", &data_base_path));
+    connection
+        .busy_timeout(DATA_BASE_BUSY_TIMEOUT)
+        .expect("Unable to set the busy timeout of the database");
+    connection
+        .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .expect("Unable to enable WAL mode on the database");
+    connection
+}
+
+pub fn get_lock_path(is_test: bool) -> String {
+    let lock_name = if is_test {
+        TEST_LOCK_FILE_NAME
+    } else {
+        LOCK_FILE_NAME
+    };
+    format!(
+        "{}{}{}",
+        get_trash_directory_path(is_test),
+        MAIN_SEPARATOR,
+        lock_name
+    )
+}
+
+// Directories previously typed in the restore prompt
+pub fn get_restore_history_path(is_test: bool) -> String {
+    let history_name = if is_test {
+        TEST_RESTORE_HISTORY_FILE_NAME
+    } else {
+        RESTORE_HISTORY_FILE_NAME
+    };
+    format!(
+        "{}{}{}",
+        get_trash_directory_path(is_test),
+        MAIN_SEPARATOR,
+        history_name
+    )
 }
 
 pub fn get_data_base_path(is_test: bool) -> String {
@@ -124,6 +232,20 @@ pub fn get_data_base_path(is_test: bool) -> String {
     )
 }
 
+pub fn get_agent_socket_path(is_test: bool) -> String {
+    let socket_name = if is_test {
+        TEST_AGENT_SOCKET_NAME
+    } else {
+        AGENT_SOCKET_NAME
+    };
+    format!(
+        "{}{}{}",
+        get_trash_directory_path(is_test),
+        MAIN_SEPARATOR,
+        socket_name
+    )
+}
+
 pub fn get_element_name(element_path_with_name: &str) -> String {
     let mut elements: Vec<&str> = element_path_with_name.split(MAIN_SEPARATOR).collect();
     elements.retain(|element| !element.is_empty());
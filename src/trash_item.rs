@@ -1,18 +1,25 @@
+use crate::config::DateFormat;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
 use field_count::FieldCount;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq, FieldCount)]
+#[derive(Debug, Clone, PartialEq, Eq, FieldCount, Serialize, Deserialize)]
 pub struct TrashItem {
     pub id: i32,
     pub name: String,
     pub hash: String,
     pub path: String,
-    pub date: String,
+    pub date: i64, // deletion time, in seconds since the epoch (UTC)
     pub real_size: u64,
     pub compression_size: Option<u64>,
     pub is_folder: bool,
     pub is_encrypted: bool,
     pub is_compressed: bool,
+    pub content_hash: Option<String>, // sha256 of the content, see checksum_manager
+    #[serde(default)]
+    pub is_pinned: bool, // never purged to free some disk space, see free_space_manager
 }
 
 impl TrashItem {
@@ -21,7 +28,7 @@ impl TrashItem {
         name: String,
         hash: String,
         path: String,
-        date: String,
+        date: i64,
         real_size: u64,
         compression_size: Option<u64>,
         is_folder: bool,
@@ -39,20 +46,87 @@ impl TrashItem {
             is_folder,
             is_encrypted,
             is_compressed,
+            content_hash: None,
+            is_pinned: false,
+        }
+    }
+
+    // Size of the element inside the trash directory, see data_manager::STORED_SIZE_SQL
+    pub fn get_stored_size(&self) -> u64 {
+        match (self.is_compressed, self.compression_size) {
+            (true, Some(compression_size)) => compression_size,
+            _ => self.real_size,
+        }
+    }
+
+    pub fn format_date(&self, date_format: DateFormat) -> String {
+        let date = DateTime::<Utc>::from_timestamp(self.date, 0).unwrap_or_default();
+        match date_format {
+            DateFormat::Local => date
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            DateFormat::Iso => date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            DateFormat::Relative => format_relative_duration(Utc::now().timestamp() - self.date),
+        }
+    }
+
+    // Display the trash item with the date in the given format
+    pub fn display_with(&self, date_format: DateFormat) -> TrashItemDisplay<'_> {
+        TrashItemDisplay {
+            trash_item: self,
+            date_format,
+        }
+    }
+}
+
+// "3 hours ago", a date in the future (a clock moved back) is "just now"
+fn format_relative_duration(seconds: i64) -> String {
+    let units = [
+        ("year", 365 * 86400),
+        ("month", 30 * 86400),
+        ("week", 7 * 86400),
+        ("day", 86400),
+        ("hour", 3600),
+        ("minute", 60),
+    ];
+    match units
+        .iter()
+        .find(|(_, unit_seconds)| seconds >= *unit_seconds)
+    {
+        Some((name, unit_seconds)) => {
+            let count = seconds / unit_seconds;
+            format!("{} {}{} ago", count, name, if count > 1 { "s" } else { "" })
         }
+        None => "just now".to_string(),
     }
 }
 
+pub struct TrashItemDisplay<'a> {
+    trash_item: &'a TrashItem,
+    date_format: DateFormat,
+}
+
 impl fmt::Display for TrashItem {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display_with(DateFormat::default()))
+    }
+}
+
+impl fmt::Display for TrashItemDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let trash_item = self.trash_item;
         let mut symbols = Vec::new();
-        if self.is_encrypted {
+        if trash_item.is_encrypted {
             symbols.push("🔒")
         };
-        if self.is_compressed {
+        if trash_item.is_compressed {
             symbols.push("📦")
         };
-        if self.is_folder {
+        if trash_item.is_pinned {
+            symbols.push("📌")
+        };
+        if trash_item.is_folder {
             symbols.push("📁")
         } else {
             symbols.push("📄")
@@ -62,9 +136,40 @@ impl fmt::Display for TrashItem {
             f,
             " {} date: {}  name: {}  initial_path: {}",
             symbols.join(""),
-            self.date,
-            self.name,
-            self.path,
+            trash_item.format_date(self.date_format),
+            trash_item.name,
+            trash_item.path,
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_date() {
+        let mut trash_item = TrashItem::new(
+            "date.txt".to_string(),
+            "date".to_string(),
+            "/home/user".to_string(),
+            1680366600,
+            1,
+            None,
+            false,
+            false,
+            false,
+        );
+        assert_eq!(
+            trash_item.format_date(DateFormat::Iso),
+            "2023-04-01T16:30:00Z"
+        );
+
+        trash_item.date = Utc::now().timestamp() - 3 * 3600 - 10;
+        assert_eq!(trash_item.format_date(DateFormat::Relative), "3 hours ago");
+        trash_item.date = Utc::now().timestamp() - 60;
+        assert_eq!(trash_item.format_date(DateFormat::Relative), "1 minute ago");
+        trash_item.date = Utc::now().timestamp() + 10;
+        assert_eq!(trash_item.format_date(DateFormat::Relative), "just now");
+    }
+}
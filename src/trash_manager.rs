@@ -1,131 +1,148 @@
 use crate::arguments_manager::ArgumentsManager;
-use crate::display_manager;
-use crate::structure_manager::{self, get_element_path, get_home_directory_path};
+use crate::checksum_manager;
+use crate::data_manager::{DirectoryVolume, TrashItemsOrder, TrashTotals};
+use crate::display_manager::{self, format_size, SizeUnits};
+use crate::encryption_manager::{self, decrypt_element, encrypt_element, EncryptionKey};
+use crate::free_space_manager;
+use crate::journal_manager::{self, JournalEntry, Operation};
+use crate::lock_manager;
+use crate::query_manager::Query;
+use crate::restore_manager::{self, RestoreOptions};
+use crate::structure_manager::{self, get_element_path, get_path_in_trash};
+use crate::version_manager;
 use crate::{
-    config::Config, data_manager, structure_manager::get_trash_directory_path,
+    config::{Config, DateFormat},
+    data_manager,
+    structure_manager::get_trash_directory_path,
     trash_item::TrashItem,
 };
 
-use chacha20poly1305::{aead::stream, KeyInit, XChaCha20Poly1305};
 use chrono;
 use colored::Colorize;
 use fs_extra::dir::{self, get_size};
 use rusqlite::Connection;
+use serde::Serialize;
 use sha256;
 use walkdir::WalkDir;
 use zip::{write::FileOptions, ZipArchive, ZipWriter};
 
-use rand::{rngs::OsRng, RngCore};
 use std::fs::{self, File};
-use std::io::{copy, stdout, Read, Write};
+use std::io::{copy, ErrorKind, Read, Write};
 use std::path::{Path, MAIN_SEPARATOR};
 
+// Move the element to the trash, return its hash in the trash (None if it is destroyed)
 pub fn add_element_to_trash(
     connection: &Connection,
     config: &Config,
     element_path: &str,
     arguments_manager: &ArgumentsManager,
-) {
+) -> Option<String> {
+    if arguments_manager.is_destroy {
+        destroy_element(element_path, arguments_manager.is_verbose);
+        return None;
+    }
+    let is_test = arguments_manager.is_test;
+
     let element_size = get_size(element_path).expect("Unable to get element size");
 
     let hash = sha256::digest(format!(
         "{}{}{}",
         &element_path,
         element_size,
-        chrono::offset::Local::now().timestamp_nanos()
+        chrono::offset::Local::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
     ));
 
-    let date = chrono::offset::Local::now().format("%Y-%m-%d %H:%M:%S");
-
-    let mut compression_size: Option<u64> = None;
-    let mut is_compressed = false;
-    let mut is_encrypted = false;
+    let date = chrono::offset::Utc::now().timestamp();
 
     let element_is_directory = Path::new(&element_path).is_dir();
+    let is_compressed = config.compression;
+    // a directory can only be encrypted once compressed
+    let is_encrypted = config.encryption && (is_compressed || !element_is_directory);
 
-    if config.compression {
-        let compressed_path = format!("{}.zip", element_path);
-        compress_element(element_path, &compressed_path).expect("Failed to compress");
-        compression_size =
-            Some(get_size(&compressed_path).expect("Unable to get compressed element size"));
-        is_compressed = true;
-
-        if config.encryption {
-            encrypt_element(
-                &compressed_path,
-                &format!(
-                    "{}{}{}",
-                    get_trash_directory_path(arguments_manager.is_test),
-                    MAIN_SEPARATOR,
-                    hash
-                ),
-            )
-            .expect("Failed to encrypt");
-            fs::remove_file(&compressed_path).unwrap();
-            is_encrypted = true;
-        } else {
-            let new_name = format!(
-                "{}{}{}",
-                get_element_path(element_path),
-                MAIN_SEPARATOR,
-                hash
+    // an element without checksums (unreadable, special file...) is trashed all the same, like
+    // the elements trashed by the older versions of rmt it is just not verified
+    let checksums = match checksum_manager::compute_element_checksums(element_path) {
+        Ok(checksums) => Some(checksums),
+        Err(e) => {
+            println!(
+                "{}: unable to compute the checksum of {} ({}), it won't be verified.",
+                "Warning".yellow().bold(),
+                element_path.bold(),
+                e
             );
-            fs::rename(&compressed_path, &new_name).unwrap();
-            fs_extra::move_items(
-                &[&new_name],
-                get_trash_directory_path(arguments_manager.is_test),
-                &dir::CopyOptions::new(),
-            )
-            .unwrap();
-        }
-        if !element_is_directory {
-            fs::remove_file(element_path).unwrap();
-        } else {
-            fs::remove_dir_all(element_path).unwrap();
+            None
         }
-    } else if config.encryption && !element_is_directory {
-        encrypt_element(
-            element_path,
-            &format!(
-                "{}{}{}",
-                get_trash_directory_path(arguments_manager.is_test),
-                MAIN_SEPARATOR,
-                hash
-            ),
-        )
-        .expect("Failed to encrypt");
-        is_encrypted = true;
-        fs::remove_file(element_path).unwrap();
+    };
+    let encryption_key = if is_encrypted {
+        Some(EncryptionKey::get(is_test, true).expect("Failed to get encryption key"))
     } else {
-        let new_name = format!(
-            "{}{}{}",
-            get_element_path(element_path),
-            MAIN_SEPARATOR,
-            hash
-        );
-        fs::rename(element_path, &new_name).unwrap();
-        fs_extra::move_items(
-            &[&new_name],
-            get_trash_directory_path(arguments_manager.is_test),
-            &dir::CopyOptions::new(),
-        )
-        .unwrap();
+        None
     };
 
-    let trash_item = TrashItem::new(
+    let mut trash_item = TrashItem::new(
         structure_manager::get_element_name(element_path),
         hash,
         get_element_path(element_path),
-        date.to_string(),
+        date,
         element_size,
-        compression_size,
+        None,
         element_is_directory,
         is_encrypted,
         is_compressed,
     );
-    if !arguments_manager.is_destroy {
-        data_manager::insert_trash_item(connection, &trash_item, arguments_manager.is_test);
+    trash_item.content_hash = checksums
+        .as_ref()
+        .map(|checksums| checksums.content_hash.clone());
+
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
+    let mut journal_entry = JournalEntry::new(
+        Operation::Add,
+        trash_item,
+        element_path.to_string(),
+        path_in_trash.clone(),
+    );
+    journal_entry.checksums = checksums
+        .map(|checksums| checksums.files)
+        .unwrap_or_default();
+    if is_compressed {
+        journal_entry.temporary_path = Some(format!("{}.zip", path_in_trash));
     }
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    data_manager::save_directories_metadata(
+        connection,
+        is_test,
+        &restore_manager::get_parents_metadata(element_path),
+    );
+    let journal_id = journal_manager::start(connection, &journal_entry, is_test);
+
+    if let Some(compressed_path) = &journal_entry.temporary_path {
+        compress_element(
+            element_path,
+            &journal_entry.trash_item.name,
+            compressed_path,
+        )
+        .expect("Failed to compress");
+        journal_entry.trash_item.compression_size =
+            Some(get_size(compressed_path).expect("Unable to get compressed element size"));
+
+        if let Some(encryption_key) = &encryption_key {
+            encrypt_element(compressed_path, &path_in_trash, encryption_key)
+                .expect("Failed to encrypt");
+            fs::remove_file(compressed_path).unwrap();
+        } else {
+            fs::rename(compressed_path, &path_in_trash).unwrap();
+        }
+    } else if let Some(encryption_key) = &encryption_key {
+        encrypt_element(element_path, &path_in_trash, encryption_key).expect("Failed to encrypt");
+    } else {
+        rename_or_copy_element(element_path, &path_in_trash).expect("Failed to move to the trash");
+    };
+
+    journal_manager::mark_copied(connection, journal_id, &mut journal_entry, is_test);
+    journal_manager::remove_path(element_path);
+    journal_manager::finish(connection, journal_id, &journal_entry, is_test);
 
     if arguments_manager.is_verbose {
         println!(
@@ -138,16 +155,64 @@ pub fn add_element_to_trash(
             element_path.green().bold()
         );
     }
+    Some(journal_entry.trash_item.hash)
+}
+
+// Rename the element when it's possible, otherwise (on another file system) copy it.
+// The source has to be removed by the caller once the copy is done.
+fn rename_or_copy_element(
+    source_path: &str,
+    dist_path: &str,
+) -> Result<(), fs_extra::error::Error> {
+    if fs::rename(source_path, dist_path).is_ok() {
+        return Ok(());
+    }
+    if Path::new(source_path).is_dir() {
+        fs::create_dir_all(dist_path)?;
+        let mut copy_options = dir::CopyOptions::new();
+        copy_options.content_only = true;
+        dir::copy(source_path, dist_path, &copy_options)?;
+    } else {
+        fs::copy(source_path, dist_path)?;
+    }
+    Ok(())
+}
+
+// Remove the element for good, without placing it in the trash
+fn destroy_element(element_path: &str, is_verbose: bool) {
+    let element_is_directory = Path::new(&element_path).is_dir();
+    if element_is_directory {
+        fs::remove_dir_all(element_path).unwrap();
+    } else {
+        fs::remove_file(element_path).unwrap();
+    }
+
+    if is_verbose {
+        println!(
+            "this {} {} has been destroyed.",
+            if element_is_directory {
+                "directory".bold().white()
+            } else {
+                "file".bold().white()
+            },
+            element_path.green().bold()
+        );
+    }
 }
 
-fn compress_element(source_path: &str, dist_path: &str) -> Result<(), std::io::Error> {
+// The element is saved in the archive under the given name, its name in the trash is its hash
+pub fn compress_element(
+    source_path: &str,
+    name: &str,
+    dist_path: &str,
+) -> Result<(), std::io::Error> {
     let mut zip_wtr = ZipWriter::new(File::create(dist_path)?);
     let zip_opts = FileOptions::default();
 
     let mut buffer = Vec::new();
     let base_path = Path::new(source_path);
     if base_path.is_file() {
-        zip_wtr.start_file(base_path.file_name().unwrap().to_string_lossy(), zip_opts)?;
+        zip_wtr.start_file(name, zip_opts)?;
         let mut f = File::open(source_path)?;
         f.read_to_end(&mut buffer)?;
         zip_wtr.write_all(&buffer)?;
@@ -155,7 +220,7 @@ fn compress_element(source_path: &str, dist_path: &str) -> Result<(), std::io::E
         zip_wtr.finish()?;
         Ok(())
     } else {
-        zip_wtr.add_directory(base_path.file_name().unwrap().to_string_lossy(), zip_opts)?;
+        zip_wtr.add_directory(name, zip_opts)?;
         for entry in WalkDir::new(source_path).into_iter().filter_map(|e| e.ok()) {
             let entry_path = entry.path();
             let entry_name = entry_path.strip_prefix(base_path).unwrap();
@@ -176,10 +241,11 @@ fn compress_element(source_path: &str, dist_path: &str) -> Result<(), std::io::E
     }
 }
 
-fn decompress_element(compressed_path: &str, dist_path: &str) -> Result<(), std::io::Error> {
+// The first entry of the archive is the element itself, it is extracted at dist_path whatever its
+// name in the archive, so an element can be restored under another name
+pub fn decompress_element(compressed_path: &str, dist_path: &str) -> Result<(), std::io::Error> {
     let mut zip_arc = ZipArchive::new(File::open(compressed_path)?)?;
 
-    let mut base_dir = dist_path.to_string();
     for i in 0..zip_arc.len() {
         let mut f = zip_arc.by_index(i)?;
         let entry_name = f
@@ -187,14 +253,10 @@ fn decompress_element(compressed_path: &str, dist_path: &str) -> Result<(), std:
             .ok_or(zip::result::ZipError::InvalidArchive("Invalid file path"))
             .expect("Failed to get extract path");
 
-        let output_path = Path::new(&base_dir).join(entry_name);
-        if i == 0 {
-            base_dir = format!(
-                "{}{}{}",
-                <&str>::clone(&dist_path),
-                MAIN_SEPARATOR,
-                entry_name.file_name().unwrap().to_string_lossy(),
-            )
+        let output_path = if i == 0 {
+            Path::new(dist_path).to_path_buf()
+        } else {
+            Path::new(dist_path).join(entry_name)
         };
 
         if f.name().ends_with('/') {
@@ -220,129 +282,6 @@ fn decompress_element(compressed_path: &str, dist_path: &str) -> Result<(), std:
     Ok(())
 }
 
-fn encrypt_element(source_path: &str, dist_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let encryption_key = dialoguer::Password::new()
-        .with_prompt("Encryption key")
-        .with_confirmation("Confirm encryption key", "Inputs do not match")
-        .interact()?;
-    let argon2_config = argon2::Config {
-        variant: argon2::Variant::Argon2id,
-        hash_length: 32,
-        lanes: 8,
-        mem_cost: 16 * 1024,
-        time_cost: 8,
-        ..Default::default()
-    };
-    let mut salt = [0u8; 32];
-    let mut nonce = [0u8; 19];
-    OsRng.fill_bytes(&mut salt);
-    OsRng.fill_bytes(&mut nonce);
-    // turn insecure passphrase into secure key
-    let key = argon2::hash_raw(encryption_key.as_bytes(), &salt, &argon2_config)?;
-    let aead = XChaCha20Poly1305::new(key[..].into());
-    let mut stream_encryptor = stream::EncryptorBE32::from_aead(aead, nonce[..].into());
-    let mut source_file = File::open(source_path)?;
-    let mut dist_file = File::create(dist_path)?;
-
-    // store salt and nonce in the encrypted file to be used when decrypting
-    dist_file.write_all(&salt)?;
-    dist_file.write_all(&nonce)?;
-    // encrypt data in small chunk of 500 bytes
-    const BUFFER_LEN: usize = 500;
-    let mut buffer = [0u8; BUFFER_LEN];
-
-    loop {
-        let read_count = source_file.read(&mut buffer)?;
-
-        if read_count == BUFFER_LEN {
-            let ciphertext = stream_encryptor.encrypt_next(buffer.as_slice());
-            match ciphertext {
-                Ok(ciphertext) => dist_file.write(&ciphertext)?,
-                Err(_) => {
-                    let _ = fs::remove_file(dist_path);
-                    panic!("Error encrypting file");
-                }
-            };
-        } else {
-            let ciphertext = stream_encryptor.encrypt_last(&buffer[..read_count]);
-            match ciphertext {
-                Ok(ciphertext) => dist_file.write(&ciphertext)?,
-                Err(_) => {
-                    let _ = fs::remove_file(dist_path);
-                    panic!("Error encrypting file");
-                }
-            };
-            break;
-        }
-    }
-    Ok(())
-}
-
-fn decrypt_element(
-    encrypted_path: &str,
-    dist_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut salt = [0u8; 32];
-    let mut nonce = [0u8; 19];
-
-    let mut encrypted_file = File::open(encrypted_path)?;
-    let mut dist_file = File::create(dist_path)?;
-
-    let mut read_count = encrypted_file.read(&mut salt)?;
-    if read_count != salt.len() {
-        panic!("Error reading salt.");
-    }
-    read_count = encrypted_file.read(&mut nonce)?;
-    if read_count != nonce.len() {
-        panic!("Error reading nonce.");
-    }
-
-    let argon2_config = argon2::Config {
-        variant: argon2::Variant::Argon2id,
-        hash_length: 32,
-        lanes: 8,
-        mem_cost: 16 * 1024,
-        time_cost: 8,
-        ..Default::default()
-    };
-    let encryption_key = dialoguer::Password::new()
-        .with_prompt("Encryption key")
-        .with_confirmation("Confirm encryption key", "Inputs do not match")
-        .interact()?;
-    let key = argon2::hash_raw(encryption_key.as_bytes(), &salt, &argon2_config).unwrap();
-
-    let aead = XChaCha20Poly1305::new(key[..32].into());
-    let mut stream_decryptor = stream::DecryptorBE32::from_aead(aead, nonce[..].into());
-    const BUFFER_LEN: usize = 500 + 16;
-    let mut buffer = [0u8; BUFFER_LEN];
-
-    loop {
-        let read_count = encrypted_file.read(&mut buffer)?;
-
-        if read_count == BUFFER_LEN {
-            let plaintext = stream_decryptor.decrypt_next(buffer.as_slice());
-            match plaintext {
-                Ok(plaintext) => dist_file.write(&plaintext)?,
-                Err(_) => {
-                    let _ = fs::remove_file(dist_path);
-                    panic!("Error decrypting file");
-                }
-            };
-        } else {
-            let plaintext = stream_decryptor.decrypt_last(&buffer[..read_count]);
-            match plaintext {
-                Ok(plaintext) => dist_file.write(&plaintext)?,
-                Err(_) => {
-                    let _ = fs::remove_file(dist_path);
-                    panic!("Error decrypting file");
-                }
-            };
-            break;
-        }
-    }
-    Ok(())
-}
-
 pub fn add_all_elements_to_trash(
     connection: &Connection,
     config: &Config,
@@ -362,7 +301,25 @@ pub fn add_all_elements_to_trash(
         let message = format!("Are you sure to delete {} ?", path.bold().green());
         if !arguments_manager.confirmation_always || display_manager::get_user_validation(&message)
         {
-            add_element_to_trash(connection, config, path, arguments_manager)
+            free_space_manager::ensure_free_space(
+                connection,
+                config,
+                arguments_manager.is_test,
+                None,
+            );
+            let trashed_hash = add_element_to_trash(connection, config, path, arguments_manager);
+            version_manager::purge_old_versions(
+                connection,
+                config,
+                arguments_manager.is_test,
+                path,
+            );
+            free_space_manager::ensure_free_space(
+                connection,
+                config,
+                arguments_manager.is_test,
+                trashed_hash.as_deref(),
+            );
         }
     }
 }
@@ -372,15 +329,20 @@ pub fn remove_all_elements_selected(
     is_test: bool,
     trash_items_ids: &[i32],
 ) {
+    let _trash_lock = lock_manager::lock_trash(is_test);
     trash_items_ids.iter().for_each(|trash_item_id| {
-        let trash_item = data_manager::find_trash_item_by_id(connection, is_test, *trash_item_id)
-            .unwrap_or_else(|_| panic!("Failed to get item with id {}", &trash_item_id));
-        remove_element(&trash_item, is_test);
-        data_manager::delete_trash_item_by_id(connection, is_test, *trash_item_id);
+        // the element may have been removed by another rmt process in the meantime
+        if let Ok(trash_item) =
+            data_manager::find_trash_item_by_id(connection, is_test, *trash_item_id)
+        {
+            remove_element(&trash_item, is_test);
+            data_manager::delete_trash_item_by_id(connection, is_test, *trash_item_id);
+        }
     });
 }
 
 pub fn remove_all_elements(connection: &Connection, is_test: bool) {
+    let _trash_lock = lock_manager::lock_trash(is_test);
     let trash_items = data_manager::find_all_trash_items(connection, is_test);
     trash_items.iter().for_each(|trash_item| {
         remove_element(trash_item, is_test);
@@ -388,17 +350,22 @@ pub fn remove_all_elements(connection: &Connection, is_test: bool) {
     data_manager::delete_all_trash_item(connection, is_test);
 }
 
-fn remove_element(trash_item: &TrashItem, is_test: bool) {
+pub fn remove_element(trash_item: &TrashItem, is_test: bool) {
     let element_path = format!(
         "{}{}{}",
         get_trash_directory_path(is_test),
         MAIN_SEPARATOR,
         trash_item.hash
     );
-    if Path::new(&element_path).is_dir() {
-        fs::remove_dir_all(&element_path).unwrap();
+    let result = if Path::new(&element_path).is_dir() {
+        fs::remove_dir_all(&element_path)
     } else {
-        fs::remove_file(&element_path).unwrap();
+        fs::remove_file(&element_path)
+    };
+    match result {
+        // already missing from the trash, its row is deleted all the same
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        result => result.unwrap(),
     }
 
     println!(
@@ -410,173 +377,727 @@ fn remove_element(trash_item: &TrashItem, is_test: bool) {
 
 pub fn restore_all_elements_selected(
     connection: &Connection,
+    config: &Config,
     is_test: bool,
     trash_items_ids: &[i32],
+    restore_options: &RestoreOptions,
 ) {
     trash_items_ids.iter().for_each(|trash_item_id| {
         let trash_item = data_manager::find_trash_item_by_id(connection, is_test, *trash_item_id)
             .unwrap_or_else(|_| panic!("Failed to get item with id {}", &trash_item_id));
-        restore_element(&trash_item, is_test);
-        data_manager::delete_trash_item_by_id(connection, is_test, *trash_item_id);
+        let restore_target = match restore_manager::resolve_restore_target(
+            connection,
+            config,
+            &trash_item,
+            restore_options,
+            is_test,
+        ) {
+            Some(restore_target) => restore_target,
+            None => return,
+        };
+        let restored_path = restore_element(
+            connection,
+            &trash_item,
+            &restore_target.restored_path,
+            is_test,
+        );
+        restore_manager::finish_created_directories(
+            connection,
+            is_test,
+            &restore_target,
+            restored_path.is_some(),
+        );
+        if let Some(restored_path) = restored_path {
+            checksum_manager::verify_restored_element(&trash_item, &restored_path);
+            if let Some(merge_path) = &restore_target.merge_path {
+                restore_manager::merge_restored_directory(&restored_path, merge_path);
+            }
+        }
     });
 }
 
-fn restore_element(trash_item: &TrashItem, is_test: bool) {
-    let path_in_trash = format!(
-        "{}{}{}",
-        get_trash_directory_path(is_test),
-        MAIN_SEPARATOR,
-        trash_item.hash
-    );
+// Restore the element, remove it from the database and return the path where it has been restored
+fn restore_element(
+    connection: &Connection,
+    trash_item: &TrashItem,
+    restored_path: &str,
+    is_test: bool,
+) -> Option<String> {
+    let restored_path = restored_path.to_string();
+    let path_in_trash = get_path_in_trash(&trash_item.hash, is_test);
 
-    let initial_path = format!("{}{}{}", &trash_item.path, MAIN_SEPARATOR, &trash_item.name);
+    let encryption_key = if trash_item.is_encrypted {
+        Some(EncryptionKey::get(is_test, false).expect("Failed to get encryption key"))
+    } else {
+        None
+    };
 
-    if Path::new(&trash_item.path).is_dir() && !Path::new(&initial_path).exists() {
-        if trash_item.is_encrypted && trash_item.is_compressed {
-            let decrypted_path_in_trash = format!(
-                "{}{}decrypted.zip",
-                get_trash_directory_path(is_test),
-                MAIN_SEPARATOR
-            );
-            decrypt_element(&path_in_trash, &decrypted_path_in_trash).expect("Failed to decrypt");
-            decompress_element(&decrypted_path_in_trash, &trash_item.path)
-                .expect("Failed to decompress");
-            fs::remove_file(decrypted_path_in_trash).unwrap();
-        } else if trash_item.is_encrypted {
-            decrypt_element(
-                &path_in_trash,
-                &format!("{}{}{}", &trash_item.path, MAIN_SEPARATOR, trash_item.name),
-            )
-            .expect("Failed to decrypt");
-            fs::remove_file(&path_in_trash).unwrap();
-        } else if trash_item.is_compressed {
-            decompress_element(&path_in_trash, &trash_item.path).expect("Failed to decompress");
-            fs::remove_file(&path_in_trash).unwrap();
-        } else {
-            let renamed_path_in_trash = format!(
-                "{}{}{}",
-                get_trash_directory_path(is_test),
-                MAIN_SEPARATOR,
-                trash_item.name
-            );
-            fs::rename(&path_in_trash, &renamed_path_in_trash).unwrap();
-            fs_extra::move_items(
-                &[&renamed_path_in_trash],
-                &trash_item.path,
-                &dir::CopyOptions::new(),
-            )
-            .unwrap();
-        };
+    let mut journal_entry = JournalEntry::new(
+        Operation::Restore,
+        trash_item.clone(),
+        path_in_trash.clone(),
+        restored_path.clone(),
+    );
+    if trash_item.is_encrypted && trash_item.is_compressed {
+        journal_entry.temporary_path = Some(format!("{}.decrypted.zip", path_in_trash));
+    }
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    // the element may have been restored or removed by another rmt process in the meantime
+    if data_manager::find_trash_item_by_id(connection, is_test, trash_item.id).is_err() {
         println!(
-            "{} has been restored ! :D\r",
+            "{} is no longer in the trash.\r",
             trash_item.name.green().bold()
         );
-        println!(
-            "You can find it at this path: {}\r",
-            initial_path.green().bold()
-        );
-        return;
+        return None;
     }
-    println!("Unfortunately Path {} doesn't exist anymore or there is a file with the same name inside, so we can't restore your element to the original path :c\r\n{}\r",
-     &trash_item.path.green().bold(), "Please enter a new absolute path to restore your element".bold());
-
-    let mut new_path = get_home_directory_path();
-    print!("{} {}", ">>".green().bold(), new_path.bold());
-    stdout().flush().unwrap();
-    std::io::stdin().read_line(&mut new_path).unwrap();
-    new_path.pop();
-    while !Path::new(&new_path).is_dir()
-        || Path::new(&format!(
-            "{}{}{}",
-            &new_path, MAIN_SEPARATOR, &trash_item.name
-        ))
-        .exists()
-    {
-        if !Path::new(&new_path).exists() {
-            println!(
-                "{} doesn't exist ! You have to give a valid {} path of a {}\r",
-                new_path.green().bold(),
-                "absolute path".green().bold(),
-                "directory".green().bold()
-            );
-        } else if !Path::new(&new_path).is_dir() {
-            println!(
-                "{} exist but it's not a {} ! \r",
-                new_path.green().bold(),
-                "directory".green().bold()
-            );
-        } else {
-            println!(
-                "{} exist and it's a {}, but it's already contain a element with the same name {}!\r",
-                new_path.green().bold(),
-                "directory".green().bold(),
-                trash_item.name.green().bold()
-            );
+    let journal_id = journal_manager::start(connection, &journal_entry, is_test);
+
+    match (&encryption_key, &journal_entry.temporary_path) {
+        (Some(encryption_key), Some(decrypted_path_in_trash)) => {
+            decrypt_element(&path_in_trash, decrypted_path_in_trash, encryption_key)
+                .expect("Failed to decrypt");
+            decompress_element(decrypted_path_in_trash, &restored_path)
+                .expect("Failed to decompress");
+            fs::remove_file(decrypted_path_in_trash).unwrap();
         }
-        new_path.clear();
-        new_path = get_home_directory_path();
-        print!("{} {}", ">>".green().bold(), new_path.bold());
-        stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut new_path).unwrap();
-        new_path.pop();
-    }
+        (Some(encryption_key), None) => {
+            decrypt_element(&path_in_trash, &restored_path, encryption_key)
+                .expect("Failed to decrypt");
+        }
+        (None, _) if trash_item.is_compressed => {
+            decompress_element(&path_in_trash, &restored_path).expect("Failed to decompress");
+        }
+        (None, _) => {
+            rename_or_copy_element(&path_in_trash, &restored_path).expect("Failed to restore");
+        }
+    };
+
+    journal_manager::mark_copied(connection, journal_id, &mut journal_entry, is_test);
+    journal_manager::remove_path(&path_in_trash);
+    journal_manager::finish(connection, journal_id, &journal_entry, is_test);
 
-    if trash_item.is_encrypted {
-        let dist_path = format!("{}{}{}", &new_path, MAIN_SEPARATOR, trash_item.name);
-        decrypt_element(&path_in_trash, &dist_path).expect("Error decrypting file");
-        fs::remove_file(&path_in_trash).unwrap();
-    } else {
-        let new_name = format!(
-            "{}{}{}",
-            get_trash_directory_path(is_test),
-            MAIN_SEPARATOR,
-            trash_item.name
-        );
-        fs::rename(&path_in_trash, &new_name).unwrap();
-        fs_extra::move_items(&[new_name], &new_path, &dir::CopyOptions::new()).unwrap();
-    }
     println!(
         "{} has been restored ! :D\r",
         trash_item.name.green().bold()
     );
-    if !new_path.is_empty() && new_path.as_bytes()[new_path.len() - 1] as char == MAIN_SEPARATOR {
-        new_path.pop();
-    }
     println!(
         "You can find it at this path: {}\r",
-        format!("{}{}{}", &new_path, MAIN_SEPARATOR, &trash_item.name)
-            .green()
-            .bold()
+        restored_path.green().bold()
     );
+    Some(restored_path)
 }
 
-pub fn display_trash_information(connection: &Connection, is_test: bool) {
-    let trash_items = data_manager::find_all_trash_items(connection, is_test);
-    let total_size = if let Some(size) = trash_items
+// Encrypt again the selected elements (or all the encrypted elements) with a new key.
+// Each element is replaced atomically, and the elements already using the new key are skipped,
+// so an interrupted rekey can simply be started again.
+pub fn rekey_elements(connection: &Connection, is_test: bool, trash_items_ids: &[i32]) -> bool {
+    let trash_items = data_manager::find_selected_trash_items(connection, is_test, trash_items_ids);
+
+    let encrypted_items: Vec<&TrashItem> = trash_items
         .iter()
-        .map(|trash_item| {
-            if trash_item.is_compressed {
-                trash_item.compression_size.unwrap_or(trash_item.real_size)
-            } else {
-                trash_item.real_size
+        .filter(|trash_item| {
+            if !trash_item.is_encrypted && !trash_items_ids.is_empty() {
+                println!(
+                    "{} is not encrypted, it will be ignored.",
+                    trash_item.name.green().bold()
+                );
             }
+            trash_item.is_encrypted
         })
-        .reduce(|a, b| a + b)
-    {
-        size
+        .collect();
+
+    if encrypted_items.is_empty() {
+        println!("There is no encrypted element to rekey.");
+        return true;
+    }
+
+    let old_key =
+        EncryptionKey::ask("Current encryption key", false).expect("Failed to get encryption key");
+    let new_key =
+        EncryptionKey::ask("New encryption key", true).expect("Failed to get encryption key");
+    let _trash_lock = lock_manager::lock_trash(is_test);
+
+    let mut is_success = true;
+    for (index, trash_item) in encrypted_items.iter().enumerate() {
+        let path_in_trash = format!(
+            "{}{}{}",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR,
+            trash_item.hash
+        );
+        let progress = format!("[{}/{}]", index + 1, encrypted_items.len());
+
+        // an interrupted rekey can leave an incomplete temporary file behind
+        let temporary_path = encryption_manager::get_rekey_temporary_path(&path_in_trash);
+        if Path::new(&temporary_path).exists() {
+            fs::remove_file(&temporary_path).unwrap();
+        }
+
+        if encryption_manager::is_valid_key(&path_in_trash, &new_key) {
+            println!(
+                "{} {} already uses the new key.",
+                progress,
+                trash_item.name.green().bold()
+            );
+            continue;
+        }
+
+        match encryption_manager::reencrypt_element(&path_in_trash, &old_key, &new_key) {
+            Ok(_) => println!(
+                "{} {} has been rekeyed.",
+                progress,
+                trash_item.name.green().bold()
+            ),
+            Err(e) => {
+                is_success = false;
+                println!(
+                    "{} {}: unable to rekey {} ({}).",
+                    progress,
+                    "Error".red().bold(),
+                    trash_item.name.green().bold(),
+                    e
+                );
+            }
+        }
+    }
+    is_success
+}
+
+// Find and display the elements matching the query (see query_manager), None if the query is
+// not valid or if nothing matches
+fn find_queried_elements(
+    connection: &Connection,
+    is_test: bool,
+    query: &str,
+    order: TrashItemsOrder,
+    date_format: DateFormat,
+) -> Option<Vec<TrashItem>> {
+    let parsed_query = match Query::parse(query) {
+        Ok(parsed_query) => parsed_query,
+        Err(e) => {
+            println!("{}", e);
+            return None;
+        }
+    };
+    let trash_items =
+        data_manager::find_queried_trash_items(connection, is_test, &parsed_query, order)
+            .expect("Failed to search the trash");
+
+    if trash_items.is_empty() {
+        if query.trim().is_empty() {
+            println!("The trash is empty.");
+        } else {
+            println!("No element of the trash matches {}.", query.green().bold());
+        }
+        return None;
+    }
+    trash_items.iter().for_each(|trash_item| {
+        println!(
+            "{} ➜ {}\r",
+            trash_item.id,
+            trash_item.display_with(date_format)
+        )
+    });
+    Some(trash_items)
+}
+
+// Display the elements matching the query. Return false if a query is given and nothing matches.
+pub fn list_elements(
+    connection: &Connection,
+    is_test: bool,
+    query: &str,
+    order: TrashItemsOrder,
+    date_format: DateFormat,
+) -> bool {
+    find_queried_elements(connection, is_test, query, order, date_format).is_some()
+        || query.trim().is_empty()
+}
+
+// Remove for good the elements matching the query, after a confirmation
+pub fn purge_elements(
+    connection: &Connection,
+    is_test: bool,
+    query: &str,
+    date_format: DateFormat,
+) -> bool {
+    let trash_items = match find_queried_elements(
+        connection,
+        is_test,
+        query,
+        TrashItemsOrder::Newest,
+        date_format,
+    ) {
+        Some(trash_items) => trash_items,
+        None => return false,
+    };
+    let message = format!(
+        "Are you sure to {} these {} elements ?",
+        "purge".green().bold(),
+        trash_items.len().to_string().green().bold()
+    );
+    if display_manager::get_user_validation(&message) {
+        let trash_items_ids: Vec<i32> =
+            trash_items.iter().map(|trash_item| trash_item.id).collect();
+        remove_all_elements_selected(connection, is_test, &trash_items_ids);
+    }
+    true
+}
+
+// Restore the elements matching the query, after a confirmation
+pub fn restore_queried_elements(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    query: &str,
+    restore_options: &RestoreOptions,
+) -> bool {
+    let trash_items = match find_queried_elements(
+        connection,
+        is_test,
+        query,
+        TrashItemsOrder::Newest,
+        config.date_format,
+    ) {
+        Some(trash_items) => trash_items,
+        None => return false,
+    };
+    let message = format!(
+        "Are you sure to {} these {} elements ?",
+        "restore".green().bold(),
+        trash_items.len().to_string().green().bold()
+    );
+    if display_manager::get_user_validation(&message) {
+        let trash_items_ids: Vec<i32> =
+            trash_items.iter().map(|trash_item| trash_item.id).collect();
+        restore_all_elements_selected(
+            connection,
+            config,
+            is_test,
+            &trash_items_ids,
+            restore_options,
+        );
+    }
+    true
+}
+
+// Pin or unpin the elements, return false if one of them doesn't exist
+pub fn pin_elements(
+    connection: &Connection,
+    is_test: bool,
+    trash_items_ids: &[i32],
+    is_pinned: bool,
+) -> bool {
+    let mut is_success = true;
+    trash_items_ids.iter().for_each(|trash_item_id| {
+        match data_manager::set_trash_item_pinned(connection, is_test, *trash_item_id, is_pinned) {
+            Ok(true) => println!(
+                "{} {}",
+                trash_item_id.to_string().green().bold(),
+                if is_pinned { "pinned" } else { "unpinned" }
+            ),
+            Ok(false) => {
+                println!(
+                    "{}: there is no element with the id {} in the trash.",
+                    "Error".red().bold(),
+                    trash_item_id.to_string().red().bold()
+                );
+                is_success = false;
+            }
+            Err(e) => {
+                println!("{}", e);
+                is_success = false;
+            }
+        }
+    });
+    is_success
+}
+
+// original size / stored size, "-" for the empty elements
+fn format_compression_ratio(original_size: u64, stored_size: u64) -> String {
+    if stored_size == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.2}", original_size as f64 / stored_size as f64)
+    }
+}
+
+// An element or a directory displayed by rmt --top
+struct TopEntry {
+    label: String,
+    path: String,
+    trash_item_id: Option<i32>, // None for a directory
+    original_size: u64,
+    stored_size: u64,
+}
+
+// Display the elements taking the most space in the trash directory, or the directories they were
+// trashed from, then offer to purge some of them
+pub fn display_top_elements(
+    connection: &Connection,
+    is_test: bool,
+    count: usize,
+    is_grouped: bool,
+    size_units: SizeUnits,
+) -> bool {
+    let top_entries: Vec<TopEntry> = if is_grouped {
+        data_manager::find_top_directories(connection, is_test, count)
+            .expect("Failed to get the directories of the trash")
+            .into_iter()
+            .map(|directory| TopEntry {
+                label: format!(
+                    "{} ({} elements)",
+                    directory.path.bold(),
+                    directory.element_count
+                ),
+                path: directory.path,
+                trash_item_id: None,
+                original_size: directory.original_size,
+                stored_size: directory.stored_size,
+            })
+            .collect()
     } else {
-        0
+        data_manager::find_largest_trash_items(connection, is_test, count)
+            .expect("Failed to get the elements of the trash")
+            .into_iter()
+            .map(|trash_item| TopEntry {
+                label: format!("{} {}", trash_item.name.bold(), trash_item.path),
+                trash_item_id: Some(trash_item.id),
+                original_size: trash_item.real_size,
+                stored_size: trash_item.get_stored_size(),
+                path: trash_item.path,
+            })
+            .collect()
     };
+
+    if top_entries.is_empty() {
+        println!("The trash is empty.");
+        return true;
+    }
+
     println!(
-        "{} elements are stored in the trash.",
-        trash_items.len().to_string().green().bold()
+        "{:>3}   {:>10} {:>10} {:>6}",
+        "",
+        "stored".bold(),
+        "original".bold(),
+        "ratio".bold()
+    );
+    top_entries
+        .iter()
+        .enumerate()
+        .for_each(|(index, top_entry)| {
+            println!(
+                "{:>3} ➜ {:>10} {:>10} {:>6}  {}\r",
+                index + 1,
+                format_size(top_entry.stored_size, size_units)
+                    .green()
+                    .bold(),
+                format_size(top_entry.original_size, size_units),
+                format_compression_ratio(top_entry.original_size, top_entry.stored_size),
+                top_entry.label
+            )
+        });
+
+    let selection = display_manager::get_user_selection(
+        &format!(
+            "Numbers of the entries to {} (nothing to keep them all):",
+            "purge".green().bold()
+        ),
+        top_entries.len(),
+    );
+    if selection.is_empty() {
+        return true;
+    }
+
+    let trash_items_ids: Vec<i32> = if is_grouped {
+        let selected_paths: Vec<&String> = selection
+            .iter()
+            .map(|index| &top_entries[*index].path)
+            .collect();
+        data_manager::find_all_trash_items(connection, is_test)
+            .into_iter()
+            .filter(|trash_item| selected_paths.contains(&&trash_item.path))
+            .map(|trash_item| trash_item.id)
+            .collect()
+    } else {
+        selection
+            .iter()
+            .filter_map(|index| top_entries[*index].trash_item_id)
+            .collect()
+    };
+    let message = format!(
+        "Are you sure to {} these {} elements ?",
+        "purge".green().bold(),
+        trash_items_ids.len().to_string().green().bold()
+    );
+    if display_manager::get_user_validation(&message) {
+        remove_all_elements_selected(connection, is_test, &trash_items_ids);
+    }
+    true
+}
+
+// Number of directories displayed in the report of the trash
+const TOP_DIRECTORIES_COUNT: usize = 5;
+
+#[derive(Serialize)]
+struct TrashItemSummary {
+    id: i32,
+    name: String,
+    path: String,
+    date: i64,
+}
+
+impl From<&TrashItem> for TrashItemSummary {
+    fn from(trash_item: &TrashItem) -> Self {
+        Self {
+            id: trash_item.id,
+            name: trash_item.name.clone(),
+            path: trash_item.path.clone(),
+            date: trash_item.date,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TrashReport {
+    #[serde(flatten)]
+    totals: TrashTotals,
+    file_count: usize,
+    compression_ratio: Option<f64>, // original size / stored size
+    oldest: Option<TrashItemSummary>,
+    newest: Option<TrashItemSummary>,
+    top_directories: Vec<DirectoryVolume>,
+}
+
+fn find_first_trash_item(
+    connection: &Connection,
+    is_test: bool,
+    order: TrashItemsOrder,
+) -> Option<TrashItem> {
+    data_manager::find_trash_items_page(connection, is_test, &Query::default(), order, 0, 1)
+        .expect("Failed to get the elements of the trash")
+        .pop()
+}
+
+// Display the statistics of the trash, as text or as JSON (the sizes are in bytes and the dates
+// are timestamps in JSON)
+pub fn display_trash_information(
+    connection: &Connection,
+    is_test: bool,
+    size_units: SizeUnits,
+    date_format: DateFormat,
+    is_json: bool,
+) {
+    let totals =
+        data_manager::get_trash_totals(connection, is_test).expect("Failed to count the trash");
+    let oldest = find_first_trash_item(connection, is_test, TrashItemsOrder::Oldest);
+    let newest = find_first_trash_item(connection, is_test, TrashItemsOrder::Newest);
+    let report = TrashReport {
+        file_count: totals.element_count - totals.folder_count,
+        compression_ratio: (totals.stored_size > 0)
+            .then(|| totals.original_size as f64 / totals.stored_size as f64),
+        oldest: oldest.as_ref().map(TrashItemSummary::from),
+        newest: newest.as_ref().map(TrashItemSummary::from),
+        top_directories: data_manager::find_top_directories(
+            connection,
+            is_test,
+            TOP_DIRECTORIES_COUNT,
+        )
+        .expect("Failed to get the directories of the trash"),
+        totals,
+    };
+
+    if is_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Unable to serialize the report")
+        );
+        return;
+    }
+
+    let totals = &report.totals;
+    println!(
+        "{} elements are stored in the trash ({} files, {} folders).",
+        totals.element_count.to_string().green().bold(),
+        report.file_count.to_string().bold(),
+        totals.folder_count.to_string().bold()
+    );
+    println!(
+        "{} encrypted, {} compressed.",
+        totals.encrypted_count.to_string().bold(),
+        totals.compressed_count.to_string().bold()
     );
     println!(
-        "{} {} is the total size of the trash.",
-        (total_size / 1000).to_string().green().bold(),
-        "ko".bold().white()
-    )
+        "{} is the original size of the elements.",
+        format_size(totals.original_size, size_units).green().bold()
+    );
+    print!(
+        "{} is the size of the trash on the disk",
+        format_size(totals.stored_size, size_units).green().bold()
+    );
+    match report.compression_ratio {
+        Some(compression_ratio) => println!(" (compression ratio {:.2}).", compression_ratio),
+        None => println!("."),
+    }
+
+    if let (Some(oldest), Some(newest)) = (&oldest, &newest) {
+        println!(
+            "The oldest element is {} ({}).",
+            oldest.name.bold(),
+            oldest.format_date(date_format)
+        );
+        println!(
+            "The newest element is {} ({}).",
+            newest.name.bold(),
+            newest.format_date(date_format)
+        );
+    }
+
+    if !report.top_directories.is_empty() {
+        println!("The directories taking the most space in the trash:");
+        report.top_directories.iter().for_each(|directory| {
+            println!(
+                "  {:>10}  {} ({} elements)",
+                format_size(directory.stored_size, size_units)
+                    .green()
+                    .bold(),
+                directory.path,
+                directory.element_count
+            )
+        });
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_restore_element() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+
+        let directory_path = format!(
+            "{}{}journal_directory",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        fs::create_dir_all(format!("{}{}sub", directory_path, MAIN_SEPARATOR)).unwrap();
+        fs::write(
+            format!(
+                "{}{}sub{}a.txt",
+                directory_path, MAIN_SEPARATOR, MAIN_SEPARATOR
+            ),
+            "rmt",
+        )
+        .unwrap();
+
+        for compression in [false, true] {
+            config.compression = compression;
+            add_element_to_trash(&connection, &config, &directory_path, &arguments_manager);
+            assert!(!Path::new(&directory_path).exists());
+
+            let trash_items = data_manager::find_all_trash_items(&connection, is_test);
+            assert_eq!(trash_items.len(), 1);
+            assert_eq!(trash_items[0].is_compressed, compression);
+            assert!(Path::new(&get_path_in_trash(&trash_items[0].hash, is_test)).exists());
+
+            restore_all_elements_selected(
+                &connection,
+                &config,
+                is_test,
+                &[trash_items[0].id],
+                &RestoreOptions::default(),
+            );
+            assert_eq!(
+                fs::read_to_string(format!(
+                    "{}{}sub{}a.txt",
+                    directory_path, MAIN_SEPARATOR, MAIN_SEPARATOR
+                ))
+                .unwrap(),
+                "rmt"
+            );
+            assert!(!Path::new(&get_path_in_trash(&trash_items[0].hash, is_test)).exists());
+            assert!(data_manager::find_all_trash_items(&connection, is_test).is_empty());
+            assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+        }
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[test]
+    fn test_remove_missing_element() {
+        let is_test = true;
+        let (config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+
+        let file_path = format!(
+            "{}{}missing_blob.txt",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        fs::write(&file_path, "rmt").unwrap();
+        add_element_to_trash(&connection, &config, &file_path, &arguments_manager);
+        let trash_items = data_manager::find_all_trash_items(&connection, is_test);
+        // removed by hand from the trash
+        fs::remove_file(get_path_in_trash(&trash_items[0].hash, is_test)).unwrap();
+
+        remove_all_elements(&connection, is_test);
+        assert!(data_manager::find_all_trash_items(&connection, is_test).is_empty());
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_element_without_checksum() {
+        let is_test = true;
+        let (config, connection) = structure_manager::setup_structure(is_test);
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+
+        // a broken symbolic link and a socket can't be read, they are trashed without checksum
+        let link_path = format!(
+            "{}{}broken_link",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        let socket_path = format!(
+            "{}{}socket",
+            get_trash_directory_path(is_test),
+            MAIN_SEPARATOR
+        );
+        std::os::unix::fs::symlink("missing_target", &link_path).unwrap();
+        let listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+        for path in [&link_path, &socket_path] {
+            add_element_to_trash(&connection, &config, path, &arguments_manager);
+            assert!(Path::new(path).symlink_metadata().is_err());
+        }
+        drop(listener);
+
+        let trash_items = data_manager::find_all_trash_items(&connection, is_test);
+        assert_eq!(trash_items.len(), 2);
+        assert!(trash_items
+            .iter()
+            .all(|trash_item| trash_item.content_hash.is_none()));
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
@@ -0,0 +1,261 @@
+// The elements trashed from the same path (the same directory and name) are the versions of this
+// path, numbered from the oldest one. rmt --versions lists them, rmt --restore-version restores
+// one of them, and max_versions in the configuration keeps only the last versions of each path.
+
+use crate::config::{Config, DateFormat};
+use crate::data_manager;
+use crate::display_manager::{format_size, SizeUnits};
+use crate::lock_manager;
+use crate::restore_manager::{self, RestoreOptions};
+use crate::structure_manager::{get_element_name, get_element_path};
+use crate::trash_item::TrashItem;
+use crate::trash_manager;
+
+use colored::Colorize;
+use rusqlite::Connection;
+use std::path::{Component, Path, PathBuf};
+
+// "~/app/./config.yml" -> ("/home/user/app", "config.yml"), the directory and name saved when an
+// element is trashed
+pub fn split_original_path(path: &str) -> Option<(String, String)> {
+    let mut normalized_path = PathBuf::new();
+    for component in Path::new(&restore_manager::to_absolute_path(path)).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized_path.pop();
+            }
+            component => normalized_path.push(component),
+        }
+    }
+    let name = normalized_path.file_name()?.to_str()?.to_string();
+    let directory = normalized_path.parent()?;
+    // the directory of a trashed element is saved without symbolic links
+    let directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf());
+    Some((directory.to_string_lossy().to_string(), name))
+}
+
+// The versions of the path, the oldest first, None (with an error) when there is none
+fn find_versions(connection: &Connection, is_test: bool, path: &str) -> Option<Vec<TrashItem>> {
+    let versions = split_original_path(path)
+        .map(|(directory, name)| {
+            data_manager::find_trash_item_versions(connection, is_test, &directory, &name)
+                .expect("Failed to get the versions of the element")
+        })
+        .unwrap_or_default();
+    if versions.is_empty() {
+        println!(
+            "{}: no element trashed from {} is in the trash.",
+            "Error".red().bold(),
+            path.red().bold()
+        );
+        return None;
+    }
+    Some(versions)
+}
+
+// List the versions of the path with their numbers, return false if there is none
+pub fn display_versions(
+    connection: &Connection,
+    is_test: bool,
+    path: &str,
+    date_format: DateFormat,
+    size_units: SizeUnits,
+) -> bool {
+    let versions = match find_versions(connection, is_test, path) {
+        Some(versions) => versions,
+        None => return false,
+    };
+    println!(
+        "{} version(s) of {}:",
+        versions.len().to_string().green().bold(),
+        path.bold()
+    );
+    let mut previous_content_hash = None;
+    for (index, trash_item) in versions.iter().enumerate() {
+        // an element trashed by an older version of rmt has no content hash
+        let is_unchanged =
+            trash_item.content_hash.is_some() && trash_item.content_hash == previous_content_hash;
+        println!(
+            "{:>4} ➜ id {:<5} {}  {:>10}{}{}",
+            (index + 1).to_string().green().bold(),
+            trash_item.id,
+            trash_item.format_date(date_format),
+            format_size(trash_item.real_size, size_units),
+            if trash_item.is_pinned { "  📌" } else { "" },
+            if is_unchanged {
+                "  (same content as the previous version)"
+                    .dimmed()
+                    .to_string()
+            } else {
+                String::new()
+            }
+        );
+        previous_content_hash = trash_item.content_hash.clone();
+    }
+    true
+}
+
+// Restore the version of the path (1 is the oldest one), return false if it doesn't exist
+pub fn restore_version(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    path: &str,
+    version: usize,
+    restore_options: &RestoreOptions,
+) -> bool {
+    let versions = match find_versions(connection, is_test, path) {
+        Some(versions) => versions,
+        None => return false,
+    };
+    let trash_item = match version.checked_sub(1).and_then(|index| versions.get(index)) {
+        Some(trash_item) => trash_item,
+        None => {
+            println!(
+                "{}: {} has {} version(s), there is no version {}.",
+                "Error".red().bold(),
+                path.bold(),
+                versions.len(),
+                version.to_string().red().bold()
+            );
+            return false;
+        }
+    };
+    trash_manager::restore_all_elements_selected(
+        connection,
+        config,
+        is_test,
+        &[trash_item.id],
+        restore_options,
+    );
+    true
+}
+
+// Purge the oldest unpinned versions of the path of a trashed element beyond the max_versions of
+// the configuration, if it is set
+pub fn purge_old_versions(
+    connection: &Connection,
+    config: &Config,
+    is_test: bool,
+    element_path: &str,
+) {
+    let max_versions = match config.trash.as_ref().and_then(|trash| trash.max_versions) {
+        // the element which has just been trashed is always kept
+        Some(max_versions) => max_versions.max(1) as usize,
+        None => return,
+    };
+    let _trash_lock = lock_manager::lock_trash(is_test);
+    let versions = data_manager::find_trash_item_versions(
+        connection,
+        is_test,
+        &get_element_path(element_path),
+        &get_element_name(element_path),
+    )
+    .expect("Failed to get the versions of the element");
+    let excess_count = versions.len().saturating_sub(max_versions);
+    if excess_count == 0 {
+        return;
+    }
+
+    println!(
+        "Only the last {} versions of {} are kept:",
+        max_versions.to_string().green().bold(),
+        element_path.bold()
+    );
+    // the pinned versions are kept but still counted, the newest one is the element just trashed
+    versions[..versions.len() - 1]
+        .iter()
+        .filter(|trash_item| !trash_item.is_pinned)
+        .take(excess_count)
+        .for_each(|trash_item| {
+            trash_manager::remove_element(trash_item, is_test);
+            data_manager::delete_trash_item_by_id(connection, is_test, trash_item.id);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arguments_manager::ArgumentsManager;
+    use crate::config::Trash;
+    use crate::structure_manager::{self, get_trash_directory_path};
+    use std::fs;
+    use std::path::MAIN_SEPARATOR;
+
+    #[test]
+    fn test_split_original_path() {
+        assert_eq!(
+            split_original_path("/rmt_missing_directory/./app/../config.yml"),
+            Some((
+                "/rmt_missing_directory".to_string(),
+                "config.yml".to_string()
+            ))
+        );
+        assert_eq!(
+            split_original_path("/rmt_missing_directory/dist/"),
+            Some(("/rmt_missing_directory".to_string(), "dist".to_string()))
+        );
+        assert_eq!(split_original_path("/"), None);
+    }
+
+    #[test]
+    fn test_purge_old_versions() {
+        let is_test = true;
+        let (mut config, connection) = structure_manager::setup_structure(is_test);
+        config.trash = Some(Trash {
+            max_size: None,
+            max_element: None,
+            min_free_space: None,
+            max_versions: Some(2),
+        });
+        let arguments_manager = ArgumentsManager {
+            is_test,
+            ..Default::default()
+        };
+        let directory = get_trash_directory_path(is_test);
+        let file_path = format!("{}{}config.yml", directory, MAIN_SEPARATOR);
+        let get_versions = || {
+            data_manager::find_trash_item_versions(&connection, is_test, &directory, "config.yml")
+                .unwrap()
+        };
+
+        for version in 1..=4 {
+            fs::write(&file_path, format!("version: {}", version)).unwrap();
+            trash_manager::add_element_to_trash(
+                &connection,
+                &config,
+                &file_path,
+                &arguments_manager,
+            );
+            if version == 1 {
+                // the first version is pinned, it is never purged
+                let pinned_id = get_versions()[0].id;
+                data_manager::set_trash_item_pinned(&connection, is_test, pinned_id, true).unwrap();
+            }
+            purge_old_versions(&connection, &config, is_test, &file_path);
+        }
+
+        let versions = get_versions();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].is_pinned);
+        assert_eq!(versions[1].real_size, "version: 4".len() as u64);
+
+        // with a single version kept, the pinned one doesn't push out the element just trashed
+        config.trash.as_mut().unwrap().max_versions = Some(1);
+        fs::write(&file_path, "version: 15").unwrap();
+        trash_manager::add_element_to_trash(&connection, &config, &file_path, &arguments_manager);
+        purge_old_versions(&connection, &config, is_test, &file_path);
+        let versions = get_versions();
+        assert_eq!(versions.len(), 2);
+        assert!(versions[0].is_pinned);
+        assert_eq!(versions[1].real_size, "version: 15".len() as u64);
+
+        connection
+            .close()
+            .expect("Unable to close sqlite connection");
+        structure_manager::clear_structure(is_test);
+    }
+}
@@ -8,7 +8,9 @@ fn unique_name() -> String {
     std::thread::sleep(time::Duration::from_nanos(1));
     sha256::digest(format!(
         "{}",
-        chrono::offset::Local::now().timestamp_nanos()
+        chrono::offset::Local::now()
+            .timestamp_nanos_opt()
+            .unwrap_or_default()
     ))
 }
 
@@ -0,0 +1,68 @@
+use assert_cmd::cargo::CommandCargoExt;
+use rmt_lib::{data_manager, structure_manager};
+use std::{fs, path::Path, process::Command};
+
+const PROCESS_COUNT: usize = 8;
+const ELEMENT_COUNT_PER_PROCESS: usize = 5;
+
+#[test]
+fn test_parallel_invocations() {
+    let is_test = true;
+    let directory = std::env::temp_dir().join(sha256::digest("rmt_parallel_invocations"));
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut all_names = Vec::new();
+    let children: Vec<_> = (0..PROCESS_COUNT)
+        .map(|process| {
+            let element_paths: Vec<String> = (0..ELEMENT_COUNT_PER_PROCESS)
+                .map(|element| {
+                    let name = format!("parallel_{}_{}.txt", process, element);
+                    let element_path = directory.join(&name);
+                    fs::write(&element_path, &name).unwrap();
+                    all_names.push(name);
+                    element_path.to_string_lossy().into_owned()
+                })
+                .collect();
+            Command::cargo_bin(env!("CARGO_PKG_NAME"))
+                .unwrap()
+                .arg("--test")
+                .args(&element_paths)
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let fsck_output = Command::cargo_bin(env!("CARGO_PKG_NAME"))
+        .unwrap()
+        .arg("--test")
+        .arg("--fsck")
+        .output()
+        .unwrap();
+    assert!(fsck_output.status.success());
+
+    let (_, connection) = structure_manager::setup_structure(is_test);
+    let trash_items = data_manager::find_all_trash_items(&connection, is_test);
+    for name in all_names.iter() {
+        let trash_item = trash_items
+            .iter()
+            .find(|trash_item| &trash_item.name == name)
+            .unwrap_or_else(|| panic!("{} is missing from the database", name));
+        assert!(Path::new(&structure_manager::get_path_in_trash(
+            &trash_item.hash,
+            is_test
+        ))
+        .exists());
+        assert!(!directory.join(name).exists());
+    }
+    assert!(data_manager::find_all_journal_entries(&connection, is_test).is_empty());
+
+    connection
+        .close()
+        .expect("Unable to close sqlite connection");
+    structure_manager::clear_structure(is_test);
+    fs::remove_dir_all(&directory).unwrap();
+}